@@ -0,0 +1,265 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use log::{debug, warn};
+
+const CACHE_EXTENSION: &str = "png";
+
+/**
+ * 按最近使用顺序记录缓存条目，总大小超过上限时淘汰最久未使用的条目
+ */
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<String, (u64, u64)>,
+    order: BTreeMap<u64, String>,
+    size: u64,
+    max_size: u64,
+    tick: u64,
+}
+
+impl Lru {
+    fn new(max_size: u64) -> Self {
+        Self {
+            max_size,
+            ..Default::default()
+        }
+    }
+
+    /**
+     * 标记为最近使用，条目不存在时返回 false
+     */
+    fn touch(&mut self, key: &str) -> bool {
+        let Some((_, tick)) = self.entries.get_mut(key) else {
+            return false;
+        };
+        self.order.remove(tick);
+        self.tick += 1;
+        *tick = self.tick;
+        self.order.insert(self.tick, key.to_string());
+        true
+    }
+
+    /**
+     * 插入条目并返回被淘汰的 key
+     */
+    fn insert(&mut self, key: &str, size: u64) -> Vec<String> {
+        self.remove(key);
+        self.tick += 1;
+        self.entries.insert(key.to_string(), (size, self.tick));
+        self.order.insert(self.tick, key.to_string());
+        self.size += size;
+
+        let mut evicted = Vec::new();
+        while self.size > self.max_size {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((size, _)) = self.entries.remove(&oldest) {
+                self.size -= size;
+            }
+            evicted.push(oldest);
+        }
+        evicted
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((size, tick)) = self.entries.remove(key) {
+            self.order.remove(&tick);
+            self.size -= size;
+        }
+    }
+}
+
+/**
+ * 渲染结果的缓存，以场景内容与渲染参数的 blake3 哈希为 key，
+ * 文件保存为 `<dir>/<key 前两位>/<key>.png`
+ */
+#[derive(Debug)]
+pub struct RenderCache {
+    dir: PathBuf,
+    lru: Mutex<Lru>,
+}
+
+impl RenderCache {
+    /**
+     * 扫描已有的缓存文件，按修改时间恢复使用顺序。
+     * 只接管文件名为合法 key 且位于对应子目录中的文件，其余文件不会被淘汰删除
+     */
+    pub fn new(dir: &Path, max_size: u64) -> Self {
+        let mut files: Vec<(SystemTime, String, u64)> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .flat_map(|shard| fs::read_dir(shard.path()).into_iter().flatten().flatten())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != CACHE_EXTENSION {
+                    return None;
+                }
+                let key = path.file_stem()?.to_str()?;
+                let shard = path.parent()?.file_name()?.to_str()?;
+                if !is_valid_key(key) || shard != &key[..2] {
+                    return None;
+                }
+                let metadata = entry.metadata().ok()?;
+                if !metadata.is_file() {
+                    return None;
+                }
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                Some((modified, key.to_string(), metadata.len()))
+            })
+            .collect();
+        files.sort();
+
+        let cache = Self {
+            dir: dir.to_path_buf(),
+            lru: Mutex::new(Lru::new(max_size)),
+        };
+        for (_, key, size) in files {
+            cache.evict(cache.lru().insert(&key, size));
+        }
+        cache
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.path(key)?;
+        if !self.lru().touch(key) {
+            return None;
+        }
+        match fs::read(path) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!("cache {}: {}", key, e);
+                self.lru().remove(key);
+                None
+            }
+        }
+    }
+
+    /**
+     * 写入失败只记录日志，不影响本次请求
+     */
+    pub fn put(&self, key: &str, data: &[u8]) {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let Some(path) = self.path(key) else {
+            warn!("cache {}: invalid key", key);
+            return;
+        };
+        let size = data.len() as u64;
+        if size > self.lru().max_size {
+            return;
+        }
+        // 先写入临时文件再重命名，避免并发请求读到不完整的图片；
+        // 临时文件名包含进程号与计数器，同一个 key 的并发写入互不影响
+        let temp_path = path.with_extension(format!(
+            "{}-{}.tmp",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temp_path, data))
+            .and_then(|_| fs::rename(&temp_path, &path));
+        if let Err(e) = result {
+            warn!("cache {}: {}", key, e);
+            let _ = fs::remove_file(&temp_path);
+            return;
+        }
+        let evicted = self.lru().insert(key, size);
+        self.evict(evicted);
+    }
+
+    fn evict(&self, keys: Vec<String>) {
+        for key in keys {
+            debug!("evict cache {}", key);
+            if let Some(path) = self.path(&key) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    /**
+     * key 不合法时返回 None，避免拼出缓存目录之外的路径
+     */
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        is_valid_key(key).then(|| {
+            self.dir
+                .join(&key[..2])
+                .join(format!("{}.{}", key, CACHE_EXTENSION))
+        })
+    }
+
+    fn lru(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/**
+ * 合法的 key 为 64 位小写十六进制的 blake3 哈希
+ */
+fn is_valid_key(key: &str) -> bool {
+    key.len() == 64 && key.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut lru = Lru::new(10);
+        assert!(lru.insert("a", 4).is_empty());
+        assert!(lru.insert("b", 4).is_empty());
+        assert!(lru.touch("a"));
+        assert_eq!(lru.insert("c", 4), vec!["b".to_string()]);
+        assert!(!lru.touch("b"));
+        assert_eq!(lru.size, 8);
+    }
+
+    #[test]
+    fn test_render_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("cache");
+        let key = |name: &str| blake3::hash(name.as_bytes()).to_hex().to_string();
+
+        let cache = RenderCache::new(&dir, 10);
+        cache.put(&key("a"), b"aaaa");
+        cache.put(&key("b"), b"bbbb");
+        assert_eq!(cache.get(&key("a")), Some(b"aaaa".to_vec()));
+        cache.put(&key("c"), b"cccc");
+        assert_eq!(cache.get(&key("b")), None);
+        assert!(!cache.path(&key("b")).unwrap().exists());
+        // 超过上限的内容不缓存
+        cache.put(&key("d"), &[0; 11]);
+        assert_eq!(cache.get(&key("d")), None);
+
+        // 不合法的 key 既不读也不写
+        cache.put("a", b"a");
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("../../etc/passwd"), None);
+
+        // 重启后恢复已有的缓存，不接管其他文件
+        fs::create_dir_all(dir.join("ab")).unwrap();
+        let stray = dir.join("ab").join("other.png");
+        fs::write(&stray, [0; 8]).unwrap();
+        let misplaced = dir.join("ab").join(format!("{}.png", key("e")));
+        fs::write(&misplaced, [0; 8]).unwrap();
+        let cache = RenderCache::new(&dir, 10);
+        assert_eq!(cache.get(&key("c")), Some(b"cccc".to_vec()));
+        cache.put(&key("f"), b"fffffff");
+        assert_eq!(cache.get(&key("c")), None);
+        assert!(stray.exists());
+        assert!(misplaced.exists());
+    }
+}
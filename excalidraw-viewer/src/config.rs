@@ -0,0 +1,153 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+
+use axum::http::HeaderValue;
+use clap::Parser;
+use excalidraw::Theme;
+
+/**
+ * 服务配置，命令行参数优先，其次是环境变量（也可以写在 .env 中）
+ */
+#[derive(Debug, Clone, Parser)]
+#[command(version, about = "将 excalidraw 场景渲染为图片的 HTTP 服务")]
+pub struct Config {
+    #[arg(long, env = "EXCALIDRAW_VIEWER_HOST", default_value = "0.0.0.0")]
+    #[arg(help = "监听地址")]
+    pub host: IpAddr,
+    #[arg(short, long, env = "EXCALIDRAW_VIEWER_PORT", default_value_t = 3300)]
+    #[arg(help = "监听端口")]
+    pub port: u16,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_ROOT", default_value = "files")]
+    #[arg(help = "场景文件所在的目录，`/file/` 后的请求路径相对于该目录")]
+    pub root: PathBuf,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_CACHE_DIR", default_value_os_t = default_cache_dir())]
+    #[arg(help = "渲染结果的缓存目录，不要与场景文件放在一起")]
+    pub cache_dir: PathBuf,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_CACHE_SIZE", default_value_t = 256)]
+    #[arg(help = "缓存大小上限（MB），超过时淘汰最久未使用的图片")]
+    pub cache_size: u64,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_PADDING", default_value_t = 100.0)]
+    #[arg(help = "请求未指定 padding 时使用的默认值")]
+    pub padding: f64,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_PIXEL", default_value_t = 4.0)]
+    #[arg(help = "请求未指定 pixel 时使用的默认缩放倍数")]
+    pub pixel: f64,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_MAX_PADDING", default_value_t = 1000.0)]
+    #[arg(help = "padding 的上限，超过时按上限处理")]
+    pub max_padding: f64,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_MAX_PIXEL", default_value_t = 8.0)]
+    #[arg(help = "pixel 的上限，超过时按上限处理")]
+    pub max_pixel: f64,
+    #[arg(
+        long,
+        env = "EXCALIDRAW_VIEWER_MAX_PIXELS",
+        default_value_t = 50_000_000
+    )]
+    #[arg(help = "输出图片的像素总数上限，超过时返回 413")]
+    pub max_pixels: u64,
+    #[arg(
+        long,
+        env = "EXCALIDRAW_VIEWER_CACHE_CONTROL",
+        default_value = "public, max-age=60"
+    )]
+    #[arg(value_parser = parse_header_value, help = "图片响应的 Cache-Control")]
+    pub cache_control: HeaderValue,
+    #[arg(long, env = "EXCALIDRAW_VIEWER_THEME", default_value = "light")]
+    #[arg(value_parser = parse_theme, help = "请求未指定 theme 时使用的默认主题：light / dark")]
+    pub theme: Theme,
+}
+
+impl Config {
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    pub fn cache_size_in_bytes(&self) -> u64 {
+        self.cache_size.saturating_mul(1024 * 1024)
+    }
+
+    /**
+     * 请求中的 padding 限制在 0 到 max_padding 之间，无法解析时使用默认值
+     */
+    pub fn clamp_padding(&self, padding: Option<f64>) -> f64 {
+        padding
+            .filter(|padding| padding.is_finite())
+            .unwrap_or(self.padding)
+            .clamp(0.0, self.max_padding.max(0.0))
+    }
+
+    /**
+     * 请求中的 pixel 必须为正数且不超过 max_pixel，否则使用默认值或上限
+     */
+    pub fn clamp_pixel(&self, pixel: Option<f64>) -> f64 {
+        pixel
+            .filter(|pixel| pixel.is_finite() && *pixel > 0.0)
+            .unwrap_or(self.pixel)
+            .min(self.max_pixel)
+    }
+
+    /**
+     * 场景范围加上 padding 并缩放后的图片尺寸，尺寸无效或像素总数超过 max_pixels 时返回 None
+     */
+    pub fn image_size(&self, width: f64, height: f64) -> Option<(usize, usize)> {
+        let to_pixels = |length: f64| {
+            (0.0..=u32::MAX as f64)
+                .contains(&length)
+                .then_some(length as usize)
+        };
+        let (width, height) = (to_pixels(width)?, to_pixels(height)?);
+        width
+            .checked_mul(height)
+            .filter(|pixels| (*pixels as u64) <= self.max_pixels)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .map(|_| (width, height))
+    }
+}
+
+fn default_cache_dir() -> PathBuf {
+    std::env::temp_dir().join("excalidraw-viewer")
+}
+
+fn parse_header_value(value: &str) -> Result<HeaderValue, String> {
+    HeaderValue::from_str(value).map_err(|e| e.to_string())
+}
+
+pub fn parse_theme(theme: &str) -> Result<Theme, String> {
+    match theme {
+        "light" => Ok(Theme::Light),
+        "dark" => Ok(Theme::Dark),
+        _ => Err(format!("unknown theme `{}`, expected light or dark", theme)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamp_render_options() {
+        let config = Config::parse_from(["excalidraw-viewer", "--max-pixel", "4"]);
+        assert_eq!(config.clamp_pixel(None), 4.0);
+        assert_eq!(config.clamp_pixel(Some(2.0)), 2.0);
+        assert_eq!(config.clamp_pixel(Some(1000.0)), 4.0);
+        assert_eq!(config.clamp_pixel(Some(-1.0)), 4.0);
+        assert_eq!(config.clamp_pixel(Some(f64::NAN)), 4.0);
+        assert_eq!(config.clamp_padding(Some(1e9)), 1000.0);
+        assert_eq!(config.clamp_padding(Some(-10.0)), 0.0);
+        assert_eq!(config.clamp_padding(Some(f64::INFINITY)), 100.0);
+    }
+
+    #[test]
+    fn test_image_size() {
+        let config = Config::parse_from(["excalidraw-viewer", "--max-pixels", "100"]);
+        assert_eq!(config.image_size(10.5, 10.0), Some((10, 10)));
+        assert_eq!(config.image_size(0.0, 0.0), Some((0, 0)));
+        assert_eq!(config.image_size(11.0, 10.0), None);
+        assert_eq!(config.image_size(1e12, 0.0), None);
+        assert_eq!(config.image_size(f64::INFINITY, 1.0), None);
+        assert_eq!(config.image_size(f64::NAN, 1.0), None);
+        assert_eq!(config.image_size(-1.0, 1.0), None);
+    }
+}
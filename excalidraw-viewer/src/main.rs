@@ -1,37 +1,65 @@
 extern crate dotenv;
+mod cache;
+mod config;
+mod scene_path;
+
 use anyhow::Result;
 use axum::{
     body::Body,
-    extract::{Path, Query},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    headers::{ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, LastModified},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::get,
     Router,
 };
-use excalidraw::Excalidraw;
-use log::debug;
-use piet_common::{kurbo::Rect, util, Color, Device, ImageFormat, RenderContext, StrokeStyle};
-use png::{ColorType, Encoder};
-use std::{collections::HashMap, fs::read_to_string, io::Cursor, net::SocketAddr};
+use cache::RenderCache;
+use clap::Parser;
+use config::Config;
+use excalidraw::{obsidian, Excalidraw, Theme};
+use log::{debug, info, warn};
+use piet_common::{util, Color, Device, ImageFormat, RenderContext};
+use std::{collections::HashMap, path::Path as FsPath, sync::Arc, time::SystemTime};
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
     env_logger::init();
-    debug!("Starting up");
-
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/file/*path", get(image_file));
+    let config = Config::parse();
+    debug!("Starting up: {:?}", config);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3300));
-    println!("listening on {}", addr);
+    let addr = config.addr();
+    info!("listening on {}", addr);
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app(config).into_make_service())
         .await
         .unwrap();
 }
 
+/**
+ * 所有请求共享的配置与渲染缓存
+ */
+struct ViewerState {
+    config: Config,
+    cache: RenderCache,
+    /**
+     * 服务启动时间，渲染结果也取决于程序本身，Last-Modified 不早于该时间
+     */
+    started_at: SystemTime,
+}
+
+fn app(config: Config) -> Router {
+    let cache = RenderCache::new(&config.cache_dir, config.cache_size_in_bytes());
+    Router::new()
+        .route("/", get(root))
+        .route("/file/*path", get(image_file))
+        .with_state(Arc::new(ViewerState {
+            config,
+            cache,
+            started_at: SystemTime::now(),
+        }))
+}
+
 async fn root() -> &'static str {
     "Hello, World!"
 }
@@ -39,116 +67,267 @@ async fn root() -> &'static str {
 #[derive(Debug, Clone)]
 struct DrawConfig {
     no_cache: bool,
-    padding: f32,
+    padding: f64,
     pixel: f64,
+    /**
+     * 在 PNG 中嵌入场景数据，可以拖回 excalidraw 继续编辑
+     */
+    embed_scene: bool,
+    /**
+     * 不绘制 viewBackgroundColor，导出透明背景的 PNG
+     */
+    transparent: bool,
+    theme: Theme,
+}
+
+impl DrawConfig {
+    /**
+     * 缓存 key：场景内容与所有影响输出的渲染参数的 blake3 哈希
+     */
+    fn cache_key(&self, content: &[u8]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update(content);
+        hasher.update(&self.padding.to_le_bytes());
+        hasher.update(&self.pixel.to_le_bytes());
+        hasher.update(&[
+            self.embed_scene as u8,
+            self.transparent as u8,
+            self.theme as u8,
+        ]);
+        hasher.finalize().to_hex().to_string()
+    }
+}
+
+/**
+ * 条件请求，If-None-Match 优先于 If-Modified-Since
+ */
+struct Conditions {
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<IfModifiedSince>,
+}
+
+impl Conditions {
+    /**
+     * 没有 If-None-Match 时 typed_get 会得到空列表，需要先判断请求头是否存在
+     */
+    fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            if_none_match: headers
+                .contains_key(header::IF_NONE_MATCH)
+                .then(|| headers.typed_get())
+                .flatten(),
+            if_modified_since: headers.typed_get(),
+        }
+    }
+
+    fn is_not_modified(&self, etag: &ETag, last_modified: Option<SystemTime>) -> bool {
+        match (&self.if_none_match, &self.if_modified_since, last_modified) {
+            (Some(if_none_match), _, _) => !if_none_match.precondition_passes(etag),
+            (None, Some(since), Some(last_modified)) => !since.is_modified(last_modified),
+            _ => false,
+        }
+    }
 }
 
 async fn image_file(
+    State(state): State<Arc<ViewerState>>,
     Path(path): Path<String>,
     Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    let file_path = format!("files/{}", path); // 请确保你有一个名为 `relative_directory` 的目录，并且里面有你想要访问的文件。
-    let nocache = params.get("nocache").is_some();
-    let padding = params
-        .get("padding")
-        .unwrap_or(&"100".to_string())
-        .parse::<f32>()
-        .unwrap_or(100.0);
-    let pixel = params
-        .get("pixel")
-        .unwrap_or(&"4".to_string())
-        .parse::<f64>()
-        .unwrap_or(4.0);
+    let config = &state.config;
+    let file_path = match scene_path::resolve(&config.root, &path) {
+        Ok(file_path) => file_path,
+        Err(e) => {
+            warn!("{}: {}", path, e);
+            return error_response(e.status(), e.to_string());
+        }
+    };
+    let nocache = params.contains_key("nocache");
+    let embed_scene = params.contains_key("embed");
+    let transparent = params.contains_key("transparent");
+    let theme = params
+        .get("theme")
+        .and_then(|theme| config::parse_theme(theme).ok())
+        .unwrap_or(config.theme);
+    let padding = config.clamp_padding(
+        params
+            .get("padding")
+            .and_then(|padding| padding.parse::<f64>().ok()),
+    );
+    let pixel = config.clamp_pixel(
+        params
+            .get("pixel")
+            .and_then(|pixel| pixel.parse::<f64>().ok()),
+    );
     let draw_config = DrawConfig {
         no_cache: nocache,
         padding,
         pixel,
+        embed_scene,
+        transparent,
+        theme,
     };
 
-    debug!("file_path: {}", file_path);
-    println!("no_catch: {:?}", nocache);
+    debug!("file_path: {}", file_path.display());
+    debug!("no_cache: {:?}", nocache);
 
-    match make_electrical_diagram(&file_path, &draw_config) {
+    let conditions = Conditions::from_headers(&headers);
+    match make_electrical_diagram(&file_path, &state, &draw_config, &conditions) {
         Ok(content) => content,
         Err(e) => {
-            let not_found = e.to_string();
-            let mut res = Response::new(Body::from(not_found));
-            *res.status_mut() = StatusCode::NOT_FOUND;
-            res
+            warn!("{}: {}", file_path.display(), e);
+            error_response(error_status(&e), e.to_string())
         }
     }
 }
 
-fn make_electrical_diagram(file_path: &str, draw_config: &DrawConfig) -> Result<Response<Body>> {
-    let file = read_to_string(file_path)?;
-    let image_file_name = format!("{}.png", file_path);
+/**
+ * 场景本身有问题时返回 422 和具体原因，而不是让 worker 崩溃；
+ * 只有文件不存在时返回 404，其余 I/O 等错误都是服务端的问题
+ */
+fn error_status(e: &anyhow::Error) -> StatusCode {
+    if e.is::<ImageTooLarge>() {
+        return StatusCode::PAYLOAD_TOO_LARGE;
+    }
+    if let Some(e) = e.downcast_ref::<scene_path::ResolveError>() {
+        return e.status();
+    }
+    if let Some(e) = e.downcast_ref::<std::io::Error>() {
+        return match e.kind() {
+            std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+    }
+    match e.downcast_ref::<excalidraw::Error>() {
+        Some(excalidraw::Error::Render { .. } | excalidraw::Error::Encode(_)) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        Some(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        None if e.is::<std::string::FromUtf8Error>() => StatusCode::UNPROCESSABLE_ENTITY,
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
 
-    if !draw_config.no_cache {
-        let hash1 = blake3::hash(&file.as_bytes());
-        let hash_file_name = format!("{}.txt", file_path);
+/**
+ * 输出图片过大或尺寸无效，不进行渲染
+ */
+#[derive(Debug)]
+struct ImageTooLarge {
+    width: f64,
+    height: f64,
+}
 
-        let saved_hash = match read_to_string(&hash_file_name) {
-            Ok(content) => content,
-            Err(_) => String::new(),
-        };
-        // 保存文件
-        std::fs::write(&hash_file_name, hash1.to_hex().to_string())?;
-        if saved_hash == hash1.to_hex().to_string() {
-            // 如果系统有这个文件就直接返回
-            if std::path::Path::new(&image_file_name).exists() {
-                let buffer = std::fs::read(&image_file_name)?;
-                let mut response = Response::new(Body::from(buffer));
-                response
-                    .headers_mut()
-                    .insert("Content-Type", "image/png".parse()?);
-                return Ok(response);
-            }
+impl std::fmt::Display for ImageTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "image size {}x{} exceeds max pixels",
+            self.width, self.height
+        )
+    }
+}
+
+impl std::error::Error for ImageTooLarge {}
+
+fn error_response(status: StatusCode, message: String) -> Response<Body> {
+    let mut res = Response::new(Body::from(message));
+    *res.status_mut() = status;
+    res
+}
+
+/**
+ * nocache 时跳过缓存读取，渲染结果仍然写入缓存。
+ * ETag 即缓存 key，客户端已有相同的图片时直接返回 304，不再渲染。
+ * Last-Modified 为场景文件修改时间与服务启动时间中较晚的一个
+ */
+fn make_electrical_diagram(
+    file_path: &FsPath,
+    state: &ViewerState,
+    draw_config: &DrawConfig,
+    conditions: &Conditions,
+) -> Result<Response<Body>> {
+    let file = std::fs::read(file_path)?;
+    // 升级或重启后渲染结果可能变化，取文件修改时间与启动时间中较晚的一个
+    let last_modified = std::fs::metadata(file_path)?
+        .modified()
+        .ok()
+        .map(|modified| modified.max(state.started_at));
+    let cache_key = draw_config.cache_key(&file);
+    let etag: ETag = format!("\"{}\"", cache_key).parse()?;
+    let mut response = Response::new(Body::empty());
+    let headers = response.headers_mut();
+    headers.typed_insert(etag.clone());
+    if let Some(last_modified) = last_modified {
+        headers.typed_insert(LastModified::from(last_modified));
+    }
+    headers.insert(header::CACHE_CONTROL, state.config.cache_control.clone());
+
+    if conditions.is_not_modified(&etag, last_modified) {
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        return Ok(response);
+    }
+    headers.insert(header::CONTENT_TYPE, "image/png".parse()?);
+    if !draw_config.no_cache {
+        if let Some(buffer) = state.cache.get(&cache_key) {
+            *response.body_mut() = Body::from(buffer);
+            return Ok(response);
         }
     }
+    let file = String::from_utf8(file)?;
 
-    let result = Excalidraw::from_json(&file)?;
+    // Obsidian 插件保存的 `.excalidraw.md` 先取出其中的场景
+    let json = match file_path
+        .extension()
+        .is_some_and(|extension| extension == "md")
+    {
+        true => obsidian::decode(&file)?,
+        false => file,
+    };
+    let (result, repairs) = Excalidraw::restore(&json)?;
+    for repair in repairs {
+        warn!("{}: {}", file_path.display(), repair);
+    }
 
-    let buffer = draw_excalidraw(&result, &image_file_name, draw_config)?;
-    let mut response = Response::new(Body::from(buffer));
-    response
-        .headers_mut()
-        .insert("Content-Type", "image/png".parse()?);
+    let buffer = draw_excalidraw(&result, &state.config, draw_config)?;
+    state.cache.put(&cache_key, &buffer);
+    *response.body_mut() = Body::from(buffer);
     Ok(response)
 }
 
 fn draw_excalidraw(
     excalidraw: &Excalidraw,
-    file_name: &str,
+    config: &Config,
     draw_config: &DrawConfig,
 ) -> Result<Vec<u8>> {
     debug!("开始绘制");
     let padding = draw_config.padding;
-    let mut device = Device::new().map_err(|e| anyhow::anyhow!("Piet error: {:?}", e))?;
     let rect = excalidraw.get_canvas_size();
-    println!("rect: {:?}", rect);
+    debug!("rect: {:?}", rect);
     let scale_factor = draw_config.pixel;
-    let width = ((rect.width + padding * 2 as f32) as f64 * scale_factor) as usize;
-    let height = ((rect.height + padding * 2 as f32) as f64 * scale_factor) as usize;
+    let (width, height) = (
+        (rect.width + padding * 2.0) * scale_factor,
+        (rect.height + padding * 2.0) * scale_factor,
+    );
+    // 场景范围没有上限，创建位图之前先检查像素总数
+    let (width, height) = config
+        .image_size(width, height)
+        .ok_or(ImageTooLarge { width, height })?;
     debug!("width: {}, height: {}", width, height);
+    let mut device = Device::new().map_err(|e| anyhow::anyhow!("Piet error: {:?}", e))?;
     let mut bitmap = device
         .bitmap_target(width, height, scale_factor)
         .map_err(|e| anyhow::anyhow!("Piet error: {:?}", e))?;
     let mut rc = bitmap.render_context();
-    let background_color = Color::from_hex_str("FFFFFF").unwrap();
-    rc.fill(
-        Rect::new(0.0, 0.0, width as f64, height as f64),
-        &background_color,
-    );
-    let stroke_style = StrokeStyle::new()
-        .line_join(piet_common::LineJoin::Round)
-        .line_cap(piet_common::LineCap::Round);
-    rc.stroke_styled(
-        Rect::new(0.0, 0.0, width as f64, height as f64),
-        &background_color,
-        width as f64,
-        &stroke_style,
-    );
-    excalidraw.draw(&mut rc, padding);
+    let background_color = match draw_config.transparent {
+        true => None,
+        false => excalidraw.get_background_color(draw_config.theme),
+    };
+    rc.clear(None, background_color.unwrap_or(Color::TRANSPARENT));
+    for e in excalidraw.draw_with_theme(&mut rc, padding, draw_config.theme) {
+        warn!("{}", e);
+    }
     rc.finish()
         .map_err(|e| anyhow::anyhow!("Piet error: {:?}", e))?;
     std::mem::drop(rc);
@@ -159,20 +338,186 @@ fn draw_excalidraw(
         .copy_raw_pixels(ImageFormat::RgbaPremul, &mut buffer)
         .map_err(|e| anyhow::anyhow!("Piet error: {:?}", e))?;
     util::unpremultiply_rgba(&mut buffer);
-    bitmap.save_to_file(file_name).expect("file save error");
-    let mut png_buffer = Vec::new();
-    {
-        let writer = Cursor::new(&mut png_buffer);
-        let mut encoder = Encoder::new(writer, width as u32, height as u32);
-        encoder.set_color(ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-        let mut writer = encoder
-            .write_header()
-            .map_err(|e| anyhow::anyhow!("Error writing PNG header: {:?}", e))?;
-        writer
-            .write_image_data(&buffer)
-            .map_err(|e| anyhow::anyhow!("Error writing PNG image data: {:?}", e))?;
-    }
+    let png_buffer = excalidraw.to_png(
+        &buffer,
+        width as u32,
+        height as u32,
+        draw_config.embed_scene,
+    )?;
     debug!("生成图片");
     Ok(png_buffer)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_reject_path_traversal() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("root")).unwrap();
+        std::fs::write(dir.path().join("secret.excalidraw"), "{}").unwrap();
+        let config = Config::parse_from([
+            "excalidraw-viewer",
+            "--root",
+            dir.path().join("root").to_str().unwrap(),
+            "--cache-dir",
+            dir.path().join("cache").to_str().unwrap(),
+        ]);
+        let app = app(config);
+
+        for (uri, status) in [
+            ("/file/../secret.excalidraw", StatusCode::FORBIDDEN),
+            ("/file/..%2Fsecret.excalidraw", StatusCode::FORBIDDEN),
+            ("/file/%2e%2e/secret.excalidraw", StatusCode::FORBIDDEN),
+            (
+                "/file/%2E%2E%2F%2E%2E%2Fetc%2Fpasswd",
+                StatusCode::FORBIDDEN,
+            ),
+            ("/file/..%5Csecret.excalidraw", StatusCode::FORBIDDEN),
+            ("/file/%2Fetc%2Fpasswd", StatusCode::NOT_FOUND),
+            // 双重编码的 `..` 只是普通的文件名
+            ("/file/%252e%252e/secret.excalidraw", StatusCode::NOT_FOUND),
+            ("/file/missing.excalidraw", StatusCode::NOT_FOUND),
+        ] {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), status, "{}", uri);
+        }
+        // 被拒绝的请求不会读写缓存
+        assert!(!dir.path().join("cache").exists());
+    }
+
+    #[tokio::test]
+    async fn test_conditional_request() {
+        let dir = TempDir::new().unwrap();
+        let scene = r#"{"type":"excalidraw","version":2,"source":"","elements":[]}"#;
+        let scene_path = dir.path().join("scene.excalidraw");
+        std::fs::write(&scene_path, scene).unwrap();
+        // 场景文件早于服务启动，Last-Modified 使用启动时间
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&scene_path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let config = Config::parse_from([
+            "excalidraw-viewer",
+            "--root",
+            dir.path().to_str().unwrap(),
+            "--cache-dir",
+            dir.path().join("cache").to_str().unwrap(),
+            "--cache-control",
+            "public, max-age=3600",
+        ]);
+        let app = app(config);
+        let request = |uri: &str, header: Option<(&str, &str)>| {
+            let mut builder = Request::builder().uri(uri);
+            if let Some((name, value)) = header {
+                builder = builder.header(name, value);
+            }
+            app.clone().oneshot(builder.body(Body::empty()).unwrap())
+        };
+
+        let response = request("/file/scene.excalidraw?pixel=1", None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::CACHE_CONTROL], "public, max-age=3600");
+        let etag = headers[header::ETAG].to_str().unwrap().to_string();
+        let last_modified = headers[header::LAST_MODIFIED].to_str().unwrap().to_string();
+        let since: LastModified = headers.typed_get().unwrap();
+        assert!(SystemTime::from(since) > modified);
+
+        let response = request(
+            "/file/scene.excalidraw?pixel=1",
+            Some(("If-None-Match", &etag)),
+        );
+        let response = response.await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        let response = request(
+            "/file/scene.excalidraw?pixel=1",
+            Some(("If-Modified-Since", &last_modified)),
+        );
+        assert_eq!(response.await.unwrap().status(), StatusCode::NOT_MODIFIED);
+        // 服务启动前的缓存可能是旧版本渲染的
+        let response = request(
+            "/file/scene.excalidraw?pixel=1",
+            Some(("If-Modified-Since", "Sun, 09 Sep 2001 01:46:40 GMT")),
+        );
+        assert_eq!(response.await.unwrap().status(), StatusCode::OK);
+
+        // 渲染参数不同时 ETag 也不同
+        let response = request(
+            "/file/scene.excalidraw?pixel=2",
+            Some(("If-None-Match", &etag)),
+        );
+        assert_eq!(response.await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_error_status() {
+        use std::io::{Error as IoError, ErrorKind};
+        let status = |e: anyhow::Error| error_status(&e);
+        assert_eq!(
+            status(IoError::from(ErrorKind::NotFound).into()),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status(IoError::from(ErrorKind::PermissionDenied).into()),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status(scene_path::ResolveError::Forbidden.into()),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(excalidraw::Error::Decode("bad scene".into()).into()),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            status(excalidraw::Error::Encode("bad buffer".into()).into()),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status(anyhow::anyhow!("Piet error")),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_large_image() {
+        let dir = TempDir::new().unwrap();
+        let scene = r##"{"type":"excalidraw","version":2,"source":"","elements":[{
+            "id": "rect", "type": "rectangle",
+            "x": 0, "y": 0, "width": 100000000, "height": 100000000, "angle": 0,
+            "strokeColor": "#1e1e1e", "backgroundColor": "transparent",
+            "fillStyle": "solid", "strokeWidth": 1, "strokeStyle": "solid",
+            "roughness": 1, "opacity": 100, "seed": 1, "version": 1,
+            "versionNonce": 1, "isDeleted": false, "updated": 1, "locked": false,
+            "roundness": null
+        }]}"##;
+        std::fs::write(dir.path().join("large.excalidraw"), scene).unwrap();
+        let config = Config::parse_from([
+            "excalidraw-viewer",
+            "--root",
+            dir.path().to_str().unwrap(),
+            "--cache-dir",
+            dir.path().join("cache").to_str().unwrap(),
+        ]);
+        let app = app(config);
+
+        let request = Request::builder()
+            .uri("/file/large.excalidraw?pixel=1")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
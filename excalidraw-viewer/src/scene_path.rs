@@ -0,0 +1,137 @@
+use std::{
+    fmt, io,
+    path::{Component, Path, PathBuf},
+};
+
+use axum::http::StatusCode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    /**
+     * 请求路径包含 `..`、绝对路径等，或者通过符号链接指向 root 之外
+     */
+    Forbidden,
+    NotFound,
+}
+
+impl ResolveError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ResolveError::Forbidden => StatusCode::FORBIDDEN,
+            ResolveError::NotFound => StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Forbidden => write!(f, "Forbidden"),
+            ResolveError::NotFound => write!(f, "Not Found"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+impl From<io::Error> for ResolveError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => ResolveError::Forbidden,
+            _ => ResolveError::NotFound,
+        }
+    }
+}
+
+/**
+ * 将请求路径（已经过 URL 解码）解析为 root 下文件的绝对路径，只允许普通的路径片段，
+ * 并且解析符号链接后仍需位于 root 之内
+ */
+pub fn resolve(root: &Path, path: &str) -> Result<PathBuf, ResolveError> {
+    if path.contains(['\0', '\\']) {
+        return Err(ResolveError::Forbidden);
+    }
+    let mut relative = PathBuf::new();
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(segment) => relative.push(segment),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(ResolveError::Forbidden)
+            }
+        }
+    }
+
+    let root = root.canonicalize()?;
+    let file = root.join(&relative).canonicalize()?;
+    if !file.starts_with(&root) {
+        return Err(ResolveError::Forbidden);
+    }
+    if !file.is_file() {
+        return Err(ResolveError::NotFound);
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /**
+     * 在临时目录下创建 root/scene.excalidraw 和 root 之外的 secret.excalidraw
+     */
+    fn create_root() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        std::fs::create_dir_all(path.join("root/nested")).unwrap();
+        std::fs::write(path.join("root/scene.excalidraw"), "{}").unwrap();
+        std::fs::write(path.join("root/nested/scene.excalidraw"), "{}").unwrap();
+        std::fs::write(path.join("secret.excalidraw"), "{}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = create_root();
+        let root = dir.path().join("root");
+
+        let scene = resolve(&root, "nested/./scene.excalidraw").unwrap();
+        assert!(scene.is_absolute());
+        assert!(scene.ends_with("root/nested/scene.excalidraw"));
+        assert_eq!(
+            resolve(&root, "missing").unwrap_err(),
+            ResolveError::NotFound
+        );
+        assert_eq!(
+            resolve(&root, "nested").unwrap_err(),
+            ResolveError::NotFound
+        );
+        // 绝对路径按 root 下的相对路径处理
+        assert!(resolve(&root, "/scene.excalidraw")
+            .unwrap()
+            .ends_with("root/scene.excalidraw"));
+
+        for path in [
+            "../secret.excalidraw",
+            "nested/../../secret.excalidraw",
+            "..\\secret.excalidraw",
+            "scene.excalidraw\0",
+        ] {
+            assert_eq!(resolve(&root, path).unwrap_err(), ResolveError::Forbidden);
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path(), root.join("escape")).unwrap();
+            std::os::unix::fs::symlink(root.join("nested"), root.join("inside")).unwrap();
+            assert_eq!(
+                resolve(&root, "escape/secret.excalidraw").unwrap_err(),
+                ResolveError::Forbidden
+            );
+            assert!(resolve(&root, "inside/scene.excalidraw")
+                .unwrap()
+                .ends_with("root/nested/scene.excalidraw"));
+        }
+    }
+}
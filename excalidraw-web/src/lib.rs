@@ -3,7 +3,7 @@ extern crate cfg_if;
 extern crate wasm_bindgen;
 use cfg_if::cfg_if;
 use excalidraw::Excalidraw;
-use log::info;
+use log::{error, info};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_console_logger::DEFAULT_LOGGER;
 cfg_if! {
@@ -28,7 +28,9 @@ pub fn run() {
 
     let mut piet_context = canvas::create_context();
 
-    Excalidraw::default().draw(&mut piet_context, 0 as f32);
+    for e in Excalidraw::default().draw(&mut piet_context, 0.0) {
+        error!("{}", e);
+    }
 
     info!("Informational message");
 }
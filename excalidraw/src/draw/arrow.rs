@@ -1,179 +1,109 @@
 use std::f64::consts::PI;
 
-use super::utils::{get_points2d, hypot, srgba_from_hex};
+use super::bound_text::{get_arrow_label_rect, get_mask_path};
+use super::utils::{
+    build_options, get_element_transform, get_points2d, get_points_bounds, get_srgba, hypot,
+};
 use super::DrawConfig;
-use crate::element::Arrowhead;
+use crate::element::{Arrowhead, StrokeStyle};
+use crate::error::Result;
 use crate::point::Point;
 use crate::{draw::utils::default_options_generator, element::Element};
 use palette::Srgba;
-use piet::kurbo::{BezPath, PathEl};
-use piet::{kurbo, RenderContext};
+use piet::kurbo::{BezPath, PathEl, Rect};
+use piet::RenderContext;
 use rough_piet::{KurboDrawable, KurboGenerator};
 use roughr::core::{FillStyle, OptionsBuilder};
 
-pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+const ELBOW_ARROW_CORNER_RADIUS: f64 = 16.0;
+
+pub fn draw(
+    ctx: &mut impl RenderContext,
+    element: &Element,
+    label: Option<&Element>,
+    config: &DrawConfig,
+) -> Result<()> {
     let mut options = OptionsBuilder::default();
-    let options = default_options_generator(element, element.roundness.is_some(), &mut options);
+    let options =
+        default_options_generator(element, element.roundness.is_some(), &mut options, config);
 
-    let _ = ctx.save();
-    ctx.transform(kurbo::Affine::translate((
-        (element.x + config.offset_x) as f64,
-        (element.y + config.offset_y) as f64,
-    )));
     let default_points = vec![Point::default(), Point::default()];
     let points = match &element.points {
         Some(points) => points,
         None => &default_points,
     };
+    if points.len() < 2 {
+        return Ok(());
+    }
+    let _ = ctx.save();
+    ctx.transform(get_element_transform(element, config));
+    if let Some(label) = label {
+        // 箭头在文字处断开
+        let label_rect = get_arrow_label_rect(element, label);
+        ctx.clip(get_mask_path(
+            get_points_bounds(points)
+                .union(label_rect)
+                .inflate(100.0, 100.0),
+            label_rect,
+        ));
+    }
     let mut shapes: Vec<KurboDrawable<f64>> = vec![];
 
-    let generator = KurboGenerator::new(
-        options
-            .clone()
-            .fill(Srgba::new(0.0, 0.0, 0.0, 0.0))
-            .build()
-            .unwrap(),
-    );
+    let generator = KurboGenerator::new(build_options(
+        options.clone().fill(Srgba::new(0.0, 0.0, 0.0, 0.0)),
+        element,
+    )?);
     let p = get_points2d(points);
 
-    let shape = if element.roundness.is_none() {
+    let shape = if element.is_elbow_arrow() {
+        generator.path(get_elbow_arrow_path(points, ELBOW_ARROW_CORNER_RADIUS))
+    } else if element.roundness.is_none() {
         generator.linear_path(&p[..], false)
     } else {
         generator.curve(&p[..])
     };
 
-    match &element.start_arrowhead {
-        Some(start_arrowhead) => match start_arrowhead {
-            Arrowhead::Arrow => {
-                let (point1, point2, point3) = get_arrow_arrowhead_point(&shape, element, true);
-                shapes
-                    .push(generator.linear_path(&[point2.to_point2d(), point1.to_point2d()], true));
-                shapes
-                    .push(generator.linear_path(&[point3.to_point2d(), point1.to_point2d()], true));
-            }
-            Arrowhead::Bar => {
-                let (point1, point2, point3) = get_bar_arrowhead_point(&shape, element, true);
-                shapes
-                    .push(generator.linear_path(&[point2.to_point2d(), point1.to_point2d()], true));
-                shapes
-                    .push(generator.linear_path(&[point3.to_point2d(), point1.to_point2d()], true));
-            }
-            Arrowhead::Dot => {
-                let default_color = Srgba::new(0.0, 0.0, 0.0, 0.0);
-                let stroke_color =
-                    srgba_from_hex(&element.stroke_color, element.opacity).unwrap_or(default_color);
-                let generator = KurboGenerator::new(
-                    options
-                        .clone()
-                        .fill(stroke_color)
-                        .fill_style(FillStyle::Solid)
-                        .build()
-                        .unwrap(),
-                );
-                let (point, r) = get_dot_arrowhead_point(&shape, element, true);
-                shapes.push(generator.circle(point.x, point.y, r));
-            }
-            Arrowhead::Triangle => {
-                let default_color = Srgba::new(0.0, 0.0, 0.0, 0.0);
-                let stroke_color =
-                    srgba_from_hex(&element.stroke_color, element.opacity).unwrap_or(default_color);
-                let generator = KurboGenerator::new(
-                    options
-                        .clone()
-                        .fill(stroke_color)
-                        .fill_style(FillStyle::Solid)
-                        .stroke_line_dash([].to_vec())
-                        .build()
-                        .unwrap(),
-                );
-                let (point1, point2, point3) = get_triangle_arrowhead_point(&shape, element, true);
-                let p = vec![
-                    point1.to_point2d(),
-                    point2.to_point2d(),
-                    point3.to_point2d(),
-                    point1.to_point2d(),
-                ];
-                shapes.push(generator.polygon(&p[..]));
-            }
-        },
-        None => {}
+    if let Some(start_arrowhead) = &element.start_arrowhead {
+        shapes.extend(get_arrowhead_shapes(
+            &shape,
+            element,
+            true,
+            start_arrowhead,
+            options,
+            config,
+        )?);
     }
-
-    match &element.end_arrowhead {
-        Some(end_arrowhead) => match end_arrowhead {
-            Arrowhead::Arrow => {
-                let (point1, point2, point3) = get_arrow_arrowhead_point(&shape, element, false);
-                shapes
-                    .push(generator.linear_path(&[point2.to_point2d(), point1.to_point2d()], true));
-                shapes
-                    .push(generator.linear_path(&[point3.to_point2d(), point1.to_point2d()], true));
-            }
-            Arrowhead::Bar => {
-                let (point1, point2, point3) = get_bar_arrowhead_point(&shape, element, false);
-                shapes
-                    .push(generator.linear_path(&[point2.to_point2d(), point1.to_point2d()], true));
-                shapes
-                    .push(generator.linear_path(&[point3.to_point2d(), point1.to_point2d()], true));
-            }
-            Arrowhead::Dot => {
-                let default_color = Srgba::new(0.0, 0.0, 0.0, 0.0);
-                let stroke_color =
-                    srgba_from_hex(&element.stroke_color, element.opacity).unwrap_or(default_color);
-                let generator = KurboGenerator::new(
-                    options
-                        .clone()
-                        .fill(stroke_color)
-                        .fill_style(FillStyle::Solid)
-                        .build()
-                        .unwrap(),
-                );
-                let (point, r) = get_dot_arrowhead_point(&shape, element, false);
-                shapes.push(generator.circle(point.x, point.y, r));
-            }
-            Arrowhead::Triangle => {
-                let default_color = Srgba::new(0.0, 0.0, 0.0, 0.0);
-                let stroke_color =
-                    srgba_from_hex(&element.stroke_color, element.opacity).unwrap_or(default_color);
-                let generator = KurboGenerator::new(
-                    options
-                        .clone()
-                        .fill(stroke_color)
-                        .fill_style(FillStyle::Solid)
-                        .stroke_line_dash([].to_vec())
-                        .build()
-                        .unwrap(),
-                );
-                let (point1, point2, point3) = get_triangle_arrowhead_point(&shape, element, false);
-                let p = vec![
-                    point1.to_point2d(),
-                    point2.to_point2d(),
-                    point3.to_point2d(),
-                    point1.to_point2d(),
-                ];
-                shapes.push(generator.polygon(&p[..]));
-            }
-        },
-        None => {}
+    if let Some(end_arrowhead) = &element.end_arrowhead {
+        shapes.extend(get_arrowhead_shapes(
+            &shape,
+            element,
+            false,
+            end_arrowhead,
+            options,
+            config,
+        )?);
     }
 
     shapes.push(shape);
 
     shapes.iter().for_each(|s| s.draw(ctx));
     let _ = ctx.restore();
+    Ok(())
 }
 
-fn get_curve_path_ops(shape: &KurboDrawable<f64>) -> &BezPath {
+fn get_curve_path_ops(shape: &KurboDrawable<f64>) -> Option<&BezPath> {
     let sets = &shape.sets;
-    for set in sets {
-        if set.op_set_type == roughr::core::OpSetType::Path {
-            return &set.ops;
-        }
-    }
-    &sets[0].ops
+    sets.iter()
+        .find(|set| set.op_set_type == roughr::core::OpSetType::Path)
+        .or(sets.first())
+        .map(|set| &set.ops)
 }
 
-fn get_start_and_end_point(ops: &BezPath, is_start: bool) -> (Point, Point) {
+fn get_start_and_end_point(ops: &BezPath, is_start: bool) -> Option<(Point, Point)> {
     let elements = ops.elements();
+    if elements.len() < 2 {
+        return None;
+    }
     let index = if is_start { 1 } else { elements.len() - 1 };
     let data = &elements[index];
     let binding = piet::kurbo::Point::default();
@@ -182,7 +112,7 @@ fn get_start_and_end_point(ops: &BezPath, is_start: bool) -> (Point, Point) {
         _ => (&binding, &binding, &binding),
     };
     let mut p0 = piet::kurbo::Point::new(0.0, 0.0);
-    let prev_op = &ops.elements()[index - 1];
+    let prev_op = &elements[index - 1];
     match prev_op {
         PathEl::MoveTo(p) => {
             p0 = p.clone();
@@ -198,135 +128,378 @@ fn get_start_and_end_point(ops: &BezPath, is_start: bool) -> (Point, Point) {
 
     let end_point = Point::new(x1, y1);
     let start_point = Point::new(x2, y2);
-    (start_point, end_point)
+    Some((start_point, end_point))
+}
+
+/**
+ * 肘形箭头的箭头方向与首尾线段保持一致，不受圆角路径影响
+ */
+fn get_elbow_arrow_segment(element: &Element, is_start: bool) -> Option<(Point, Point)> {
+    if !element.is_elbow_arrow() {
+        return None;
+    }
+    let points = element.points.as_ref()?;
+    if points.len() < 2 {
+        return None;
+    }
+    if is_start {
+        Some((points[0], points[1]))
+    } else {
+        Some((points[points.len() - 1], points[points.len() - 2]))
+    }
 }
 
-fn get_arrow_point(start_point: Point, end_point: Point, length: f64, size: f64) -> Point {
+/**
+ * 生成肘形箭头路径：直角转折处使用二次贝塞尔曲线圆角，与 excalidraw 的 generateElbowArrowShape 一致
+ */
+fn get_elbow_arrow_path(points: &[Point], radius: f64) -> String {
+    let is_horizontal =
+        |point: &Point, origin: &Point| (point.x - origin.x).abs() >= (point.y - origin.y).abs();
+    let get_corner_point = |point: &Point, other: &Point, horizontal: bool, corner: f64| {
+        if horizontal {
+            let x = if other.x < point.x {
+                point.x - corner
+            } else {
+                point.x + corner
+            };
+            Point::new(x, point.y)
+        } else {
+            let y = if other.y < point.y {
+                point.y - corner
+            } else {
+                point.y + corner
+            };
+            Point::new(point.x, y)
+        }
+    };
+
+    let mut path = match points.first() {
+        Some(first) => format!("M {} {}", first.x, first.y),
+        None => return String::new(),
+    };
+    for i in 1..points.len().saturating_sub(1) {
+        let prev = &points[i - 1];
+        let point = &points[i];
+        let next = &points[i + 1];
+        let corner = radius
+            .min(hypot(next.x - point.x, next.y - point.y) / 2.0)
+            .min(hypot(prev.x - point.x, prev.y - point.y) / 2.0);
+        let before = get_corner_point(point, prev, is_horizontal(point, prev), corner);
+        let after = get_corner_point(point, next, is_horizontal(next, point), corner);
+        path.push_str(&format!(
+            " L {} {} Q {} {}, {} {}",
+            before.x, before.y, point.x, point.y, after.x, after.y
+        ));
+    }
+    if let Some(last) = points.last() {
+        if points.len() > 1 {
+            path.push_str(&format!(" L {} {}", last.x, last.y));
+        }
+    }
+    path
+}
+
+/**
+ * 两点重合时无法确定箭头方向，返回 None
+ */
+fn get_arrow_point(start_point: Point, end_point: Point, min_size: f64) -> Option<Point> {
     let x2 = start_point.x;
     let y2 = start_point.y;
     let x1 = end_point.x;
     let y1 = end_point.y;
 
     let distance = hypot(x2 - x1, y2 - y1);
+    if !distance.is_finite() || distance == 0.0 {
+        return None;
+    }
     let nx = (x2 - x1) / distance;
     let ny = (y2 - y1) / distance;
 
-    let min_size = size.min(length / 2.0);
     let xs = x2 - nx * min_size;
     let ys = y2 - ny * min_size;
-    Point::new(xs, ys)
+    Some(Point::new(xs, ys))
+}
+
+enum ArrowheadPoints {
+    /// 圆心与直径
+    Circle(Point, f64),
+    /// 汇聚点与两侧端点
+    Lines(Point, Point, Point),
+    /// 顶点、一侧端点、对角点、另一侧端点
+    Diamond(Point, Point, Point, Point),
+}
+
+fn get_arrowhead_size(arrowhead: &Arrowhead) -> f64 {
+    match arrowhead {
+        Arrowhead::Arrow => 25.0,
+        Arrowhead::Diamond | Arrowhead::DiamondOutline => 12.0,
+        Arrowhead::CrowfootOne | Arrowhead::CrowfootMany | Arrowhead::CrowfootOneOrMany => 20.0,
+        _ => 15.0,
+    }
+}
+
+fn get_arrowhead_angle(arrowhead: &Arrowhead) -> f64 {
+    match arrowhead {
+        Arrowhead::Bar => 90.0,
+        Arrowhead::Arrow => 20.0,
+        _ => 25.0,
+    }
 }
 
 /**
- * 生成箭头数据
+ * 生成箭头数据，与 excalidraw 的 getArrowheadPoints 保持一致
  */
-fn get_arrow_arrowhead_point(
+fn get_arrowhead_points(
     shape: &KurboDrawable<f64>,
     element: &Element,
     is_start: bool,
-) -> (Point, Point, Point) {
-    let ops = get_curve_path_ops(shape);
-    let (start_point, end_point) = get_start_and_end_point(ops, is_start);
-
-    let mut length = 0.0;
+    arrowhead: &Arrowhead,
+) -> Option<ArrowheadPoints> {
+    let (start_point, end_point) = match get_elbow_arrow_segment(element, is_start) {
+        Some(segment) => segment,
+        None => get_start_and_end_point(get_curve_path_ops(shape)?, is_start)?,
+    };
+    compute_arrowhead_points(element, is_start, arrowhead, start_point, end_point)
+}
 
-    // arrowhead === arrow
-    // Length for -> arrows is based on the length of the last section
-    if let Some(points) = &element.points {
-        let point1 = points.last().unwrap();
-        let mut point2 = Point::default();
-        if points.len() > 1 {
-            point2 = points[points.len() - 2].clone();
+/**
+ * 箭头的外接矩形（相对于元素原点），箭头方向取首尾线段的方向
+ */
+pub(crate) fn get_arrowhead_bounds(
+    element: &Element,
+    is_start: bool,
+    arrowhead: &Arrowhead,
+) -> Option<Rect> {
+    let points = element.points.as_ref().filter(|points| points.len() >= 2)?;
+    let (start_point, end_point) = if is_start {
+        (points[0], points[1])
+    } else {
+        (points[points.len() - 1], points[points.len() - 2])
+    };
+    let rect = match compute_arrowhead_points(element, is_start, arrowhead, start_point, end_point)?
+    {
+        ArrowheadPoints::Circle(center, diameter) => {
+            Rect::from_center_size((center.x, center.y), (diameter, diameter))
         }
-        length = hypot(point1.x - point2.x, point1.y - point2.y);
-    }
-    let arrow_point = get_arrow_point(start_point, end_point, length, 30.0);
-
-    let angle = 20.0;
-    let point1 = rotate(arrow_point, start_point, (-angle * PI) / 180.0);
-    let point2 = rotate(arrow_point, start_point, (angle * PI) / 180.0);
-    (start_point, point1, point2)
+        ArrowheadPoints::Lines(point1, point2, point3) => {
+            get_points_bounds(&[point1, point2, point3])
+        }
+        ArrowheadPoints::Diamond(point1, point2, point3, point4) => {
+            get_points_bounds(&[point1, point2, point3, point4])
+        }
+    };
+    rect.is_finite().then_some(rect)
 }
 
-fn get_bar_arrowhead_point(
-    shape: &KurboDrawable<f64>,
+fn compute_arrowhead_points(
     element: &Element,
     is_start: bool,
-) -> (Point, Point, Point) {
-    let ops = get_curve_path_ops(shape);
-    let (start_point, end_point) = get_start_and_end_point(ops, is_start);
-
+    arrowhead: &Arrowhead,
+    start_point: Point,
+    end_point: Point,
+) -> Option<ArrowheadPoints> {
+    // Length is based on the length of the last section
     let mut length = 0.0;
-
-    // Length for other arrowhead types is based on the total length of the line
+    let mut prev_point = Point::default();
     if let Some(points) = &element.points {
-        for (index, point) in points.iter().enumerate() {
-            if index == 0 {
-                continue;
-            }
-            let prev_point = &points[index - 1];
-            length += hypot(point.x - prev_point.x, point.y - prev_point.y);
+        let (point, prev) = if is_start {
+            (points.first(), points.get(1))
+        } else {
+            (
+                points.last(),
+                points.len().checked_sub(2).and_then(|i| points.get(i)),
+            )
+        };
+        prev_point = prev.copied().unwrap_or_default();
+        if let Some(point) = point {
+            length = hypot(point.x - prev_point.x, point.y - prev_point.y);
         }
     }
-    let arrow_point = get_arrow_point(start_point, end_point, length, 15.0);
 
-    let angle = 90.0;
+    // Scale down the arrowhead until we hit a certain size so that it doesn't look weird.
+    let length_multiplier = match arrowhead {
+        Arrowhead::Diamond | Arrowhead::DiamondOutline => 0.25,
+        _ => 0.5,
+    };
+    let min_size = get_arrowhead_size(arrowhead).min(length * length_multiplier);
+    let arrow_point = get_arrow_point(start_point, end_point, min_size)?;
+
+    if matches!(
+        arrowhead,
+        Arrowhead::Dot | Arrowhead::Circle | Arrowhead::CircleOutline
+    ) {
+        let diameter = hypot(arrow_point.y - start_point.y, arrow_point.x - start_point.x)
+            + element.stroke_width
+            - 2.0;
+        return Some(ArrowheadPoints::Circle(start_point, diameter));
+    }
+
+    let angle = get_arrowhead_angle(arrowhead);
+    if matches!(
+        arrowhead,
+        Arrowhead::CrowfootMany | Arrowhead::CrowfootOneOrMany
+    ) {
+        let point1 = rotate(start_point, arrow_point, (-angle * PI) / 180.0);
+        let point2 = rotate(start_point, arrow_point, (angle * PI) / 180.0);
+        return Some(ArrowheadPoints::Lines(arrow_point, point1, point2));
+    }
+
     let point1 = rotate(arrow_point, start_point, (-angle * PI) / 180.0);
     let point2 = rotate(arrow_point, start_point, (angle * PI) / 180.0);
-    (start_point, point1, point2)
+    if matches!(arrowhead, Arrowhead::Diamond | Arrowhead::DiamondOutline) {
+        // point opposite to the arrowhead point
+        let opposite = if is_start {
+            rotate(
+                Point::new(start_point.x + min_size * 2.0, start_point.y),
+                start_point,
+                (prev_point.y - start_point.y).atan2(prev_point.x - start_point.x),
+            )
+        } else {
+            rotate(
+                Point::new(start_point.x - min_size * 2.0, start_point.y),
+                start_point,
+                (start_point.y - prev_point.y).atan2(start_point.x - prev_point.x),
+            )
+        };
+        return Some(ArrowheadPoints::Diamond(
+            start_point,
+            point1,
+            opposite,
+            point2,
+        ));
+    }
+    Some(ArrowheadPoints::Lines(start_point, point1, point2))
 }
 
-fn get_dot_arrowhead_point(
+fn get_arrowhead_shapes(
     shape: &KurboDrawable<f64>,
     element: &Element,
     is_start: bool,
-) -> (Point, f64) {
-    let ops = get_curve_path_ops(shape);
-    let (start_point, end_point) = get_start_and_end_point(ops, is_start);
-    let mut length = 0.0;
+    arrowhead: &Arrowhead,
+    options: &OptionsBuilder,
+    config: &DrawConfig,
+) -> Result<Vec<KurboDrawable<f64>>> {
+    let default_color = Srgba::new(0.0, 0.0, 0.0, 0.0);
+    let stroke_color =
+        get_srgba(&element.stroke_color, element.opacity, config).unwrap_or(default_color);
+    // outline 类型的箭头使用画布背景色填充
+    let background_color = get_srgba(&config.background_color, element.opacity, config)
+        .or_else(|| get_srgba("#ffffff", element.opacity, config))
+        .unwrap_or(default_color);
+    let is_outline = matches!(
+        arrowhead,
+        Arrowhead::CircleOutline | Arrowhead::TriangleOutline | Arrowhead::DiamondOutline
+    );
+    let fill_color = if is_outline {
+        background_color
+    } else {
+        stroke_color
+    };
 
-    // Length for other arrowhead types is based on the total length of the line
-    if let Some(points) = &element.points {
-        for (index, point) in points.iter().enumerate() {
-            if index == 0 {
-                continue;
-            }
-            let prev_point = &points[index - 1];
-            length += hypot(point.x - prev_point.x, point.y - prev_point.y);
+    let points = match get_arrowhead_points(shape, element, is_start, arrowhead) {
+        Some(points) => points,
+        None => return Ok(vec![]),
+    };
+    let shapes = match points {
+        ArrowheadPoints::Circle(point, diameter) => {
+            let generator = KurboGenerator::new(build_options(
+                options
+                    .clone()
+                    .fill(fill_color)
+                    .fill_style(FillStyle::Solid)
+                    .stroke_line_dash(vec![])
+                    .roughness(element.roughness.min(0.5) as f32),
+                element,
+            )?);
+            vec![generator.circle(point.x, point.y, diameter)]
         }
-    }
-    let arrow_point = get_arrow_point(start_point, end_point, length, 15.0);
-    let r = hypot(arrow_point.y - start_point.y, arrow_point.x - start_point.x)
-        + element.stroke_width as f64;
-    (start_point, r)
+        ArrowheadPoints::Diamond(point1, point2, point3, point4) => {
+            let generator = KurboGenerator::new(build_options(
+                options
+                    .clone()
+                    .fill(fill_color)
+                    .fill_style(FillStyle::Solid)
+                    .stroke_line_dash(vec![])
+                    .roughness(element.roughness.min(1.0) as f32),
+                element,
+            )?);
+            let p = [
+                point1.to_point2d(),
+                point2.to_point2d(),
+                point3.to_point2d(),
+                point4.to_point2d(),
+                point1.to_point2d(),
+            ];
+            vec![generator.polygon(&p)]
+        }
+        ArrowheadPoints::Lines(point1, point2, point3) => match arrowhead {
+            Arrowhead::Triangle | Arrowhead::TriangleOutline => {
+                let generator = KurboGenerator::new(build_options(
+                    options
+                        .clone()
+                        .fill(fill_color)
+                        .fill_style(FillStyle::Solid)
+                        .stroke_line_dash(vec![])
+                        .roughness(element.roughness.min(1.0) as f32),
+                    element,
+                )?);
+                let p = [
+                    point1.to_point2d(),
+                    point2.to_point2d(),
+                    point3.to_point2d(),
+                    point1.to_point2d(),
+                ];
+                vec![generator.polygon(&p)]
+            }
+            Arrowhead::CrowfootOne => {
+                let generator = get_arrowhead_line_generator(element, options)?;
+                vec![generator.line(point2.x, point2.y, point3.x, point3.y)]
+            }
+            Arrowhead::CrowfootOneOrMany => {
+                let mut shapes = get_arrowhead_shapes(
+                    shape,
+                    element,
+                    is_start,
+                    &Arrowhead::CrowfootOne,
+                    options,
+                    config,
+                )?;
+                let generator = get_arrowhead_line_generator(element, options)?;
+                shapes.push(generator.line(point2.x, point2.y, point1.x, point1.y));
+                shapes.push(generator.line(point3.x, point3.y, point1.x, point1.y));
+                shapes
+            }
+            _ => {
+                let generator = get_arrowhead_line_generator(element, options)?;
+                vec![
+                    generator.line(point2.x, point2.y, point1.x, point1.y),
+                    generator.line(point3.x, point3.y, point1.x, point1.y),
+                ]
+            }
+        },
+    };
+    Ok(shapes)
 }
 
-fn get_triangle_arrowhead_point(
-    shape: &KurboDrawable<f64>,
+/**
+ * 线条类箭头：虚线箭头保持实线，点线箭头缩小间隔便于辨认
+ */
+fn get_arrowhead_line_generator(
     element: &Element,
-    is_start: bool,
-) -> (Point, Point, Point) {
-    let ops = get_curve_path_ops(shape);
-    let (start_point, end_point) = get_start_and_end_point(ops, is_start);
-
-    let mut length = 0.0;
-
-    // Length for other arrowhead types is based on the total length of the line
-    if let Some(points) = &element.points {
-        for (index, point) in points.iter().enumerate() {
-            if index == 0 {
-                continue;
-            }
-            let prev_point = &points[index - 1];
-            length += hypot(point.x - prev_point.x, point.y - prev_point.y);
-        }
-    }
-    let arrow_point = get_arrow_point(start_point, end_point, length, 15.0);
-
-    let angle = 25.0;
-    let point1 = rotate(arrow_point, start_point, (-angle * PI) / 180.0);
-    let point2 = rotate(arrow_point, start_point, (angle * PI) / 180.0);
-    (start_point, point1, point2)
+    options: &OptionsBuilder,
+) -> Result<KurboGenerator> {
+    let stroke_line_dash = if element.stroke_style == StrokeStyle::Dotted {
+        vec![1.5, 6.0 + element.stroke_width - 2.0]
+    } else {
+        vec![]
+    };
+    Ok(KurboGenerator::new(build_options(
+        options
+            .clone()
+            .stroke_line_dash(stroke_line_dash)
+            .roughness(element.roughness.min(1.0) as f32),
+        element,
+    )?))
 }
 
 fn equation(
@@ -353,3 +526,57 @@ fn rotate(point1: Point, point2: Point, angle: f64) -> Point {
     let y = (point1.x - point2.x) * angle.sin() + (point1.y - point2.y) * angle.cos() + point2.y;
     Point::new(x, y)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_elbow_arrow_path() {
+        let points = vec![
+            Point::new(0.0, 0.0),
+            Point::new(100.0, 0.0),
+            Point::new(100.0, 40.0),
+            Point::new(120.0, 40.0),
+        ];
+        assert_eq!(
+            get_elbow_arrow_path(&points, 16.0),
+            "M 0 0 L 84 0 Q 100 0, 100 16 L 100 30 Q 100 40, 110 40 L 120 40"
+        );
+    }
+
+    #[test]
+    fn test_arrowhead_skipped_for_coincident_points() {
+        let mut element = Element {
+            element_type: crate::element::ElementType::Arrow,
+            points: Some(vec![Point::new(0.0, 0.0), Point::new(0.0, 0.0)]),
+            end_arrowhead: Some(Arrowhead::Arrow),
+            ..Default::default()
+        };
+        assert!(get_arrow_point(Point::new(1.0, 1.0), Point::new(1.0, 1.0), 10.0).is_none());
+        assert!(get_arrowhead_bounds(&element, false, &Arrowhead::Arrow).is_none());
+        let mut ctx = piet::NullRenderContext::new();
+        assert!(draw(&mut ctx, &element, None, &DrawConfig::default()).is_ok());
+
+        element.points = Some(vec![Point::new(0.0, 0.0), Point::new(100.0, 0.0)]);
+        assert!(get_arrowhead_bounds(&element, false, &Arrowhead::Arrow).is_some());
+    }
+
+    #[test]
+    fn test_elbow_arrow_segment() {
+        let element = Element {
+            element_type: crate::element::ElementType::Arrow,
+            elbowed: Some(true),
+            points: Some(vec![
+                Point::new(0.0, 0.0),
+                Point::new(100.0, 0.0),
+                Point::new(100.0, 20.0),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_elbow_arrow_segment(&element, false),
+            Some((Point::new(100.0, 20.0), Point::new(100.0, 0.0)))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::{
+    element::{Element, ElementType, TextAlign, VerticalAlign},
+    point::Point,
+};
+use piet::kurbo::{BezPath, Rect};
+
+pub const BOUND_TEXT_PADDING: f64 = 5.0;
+
+/**
+ * 获取文字所在的容器
+ */
+pub fn get_container<'a>(
+    element: &Element,
+    element_map: &HashMap<&str, &'a Element>,
+) -> Option<&'a Element> {
+    let container_id = element.container_id.as_deref()?;
+    element_map
+        .get(container_id)
+        .copied()
+        .filter(|container| !container.is_deleted)
+}
+
+/**
+ * 获取容器内绑定的文字
+ */
+pub fn get_bound_text<'a>(
+    container: &Element,
+    element_map: &HashMap<&str, &'a Element>,
+) -> Option<&'a Element> {
+    container
+        .bound_elements
+        .as_ref()?
+        .iter()
+        .filter(|bound| bound.type_field == ElementType::Text)
+        .filter_map(|bound| element_map.get(bound.id.as_str()).copied())
+        .find(|text| !text.is_deleted && text.text.as_deref().is_some_and(|t| !t.is_empty()))
+}
+
+/**
+ * 计算绑定文字左上角的绝对坐标
+ */
+pub fn get_bound_text_position(text: &Element, container: &Element) -> (f64, f64) {
+    if container.element_type == ElementType::Arrow {
+        let midpoint = get_arrow_midpoint(container);
+        return (
+            container.x + midpoint.x - text.width / 2.0,
+            container.y + midpoint.y - text.height / 2.0,
+        );
+    }
+    let (offset_x, offset_y) = get_container_offset(container);
+    let max_width = get_bound_text_max_width(container);
+    let max_height = get_bound_text_max_height(container);
+    let x = match text.text_align.unwrap_or_default() {
+        TextAlign::Left => offset_x,
+        TextAlign::Center => offset_x + (max_width - text.width) / 2.0,
+        TextAlign::Right => offset_x + max_width - text.width,
+    };
+    let y = match text.vertical_align.unwrap_or_default() {
+        VerticalAlign::Top => offset_y,
+        VerticalAlign::Middle => offset_y + (max_height - text.height) / 2.0,
+        VerticalAlign::Bottom => offset_y + max_height - text.height,
+    };
+    (container.x + x, container.y + y)
+}
+
+/**
+ * 箭头上文字所占的区域（相对于箭头原点），用于在绘制箭头时挖空
+ */
+pub fn get_arrow_label_rect(arrow: &Element, text: &Element) -> Rect {
+    let (x, y) = get_bound_text_position(text, arrow);
+    let padding = BOUND_TEXT_PADDING;
+    Rect::new(
+        (x - arrow.x) - padding,
+        (y - arrow.y) - padding,
+        (x - arrow.x + text.width) + padding,
+        (y - arrow.y + text.height) + padding,
+    )
+}
+
+/**
+ * 生成挖去 hole 的裁剪路径，outer 顺时针、hole 逆时针，
+ * 无论 nonzero 还是 evenodd 填充规则都能得到镂空效果
+ */
+pub fn get_mask_path(outer: Rect, hole: Rect) -> BezPath {
+    let mut path = BezPath::new();
+    path.move_to((outer.x0, outer.y0));
+    path.line_to((outer.x1, outer.y0));
+    path.line_to((outer.x1, outer.y1));
+    path.line_to((outer.x0, outer.y1));
+    path.close_path();
+    path.move_to((hole.x0, hole.y0));
+    path.line_to((hole.x0, hole.y1));
+    path.line_to((hole.x1, hole.y1));
+    path.line_to((hole.x1, hole.y0));
+    path.close_path();
+    path
+}
+
+/**
+ * 箭头的中点：奇数个点取中间的点，偶数个点取中间线段的中点
+ */
+fn get_arrow_midpoint(arrow: &Element) -> Point {
+    let points = match &arrow.points {
+        Some(points) if !points.is_empty() => points,
+        _ => return Point::new(arrow.width / 2.0, arrow.height / 2.0),
+    };
+    let index = points.len() / 2;
+    if points.len() % 2 == 1 {
+        return points[index];
+    }
+    let p1 = points[index - 1];
+    let p2 = points[index];
+    Point::new((p1.x + p2.x) / 2.0, (p1.y + p2.y) / 2.0)
+}
+
+fn get_container_offset(container: &Element) -> (f64, f64) {
+    match container.element_type {
+        ElementType::Ellipse => (
+            (container.width / 2.0) * (1.0 - std::f64::consts::FRAC_1_SQRT_2),
+            (container.height / 2.0) * (1.0 - std::f64::consts::FRAC_1_SQRT_2),
+        ),
+        ElementType::Diamond => (container.width / 4.0, container.height / 4.0),
+        _ => (BOUND_TEXT_PADDING, BOUND_TEXT_PADDING),
+    }
+}
+
+fn get_bound_text_max_width(container: &Element) -> f64 {
+    let width = container.width;
+    match container.element_type {
+        ElementType::Ellipse => {
+            (width / 2.0 * std::f64::consts::SQRT_2).round() - BOUND_TEXT_PADDING * 2.0
+        }
+        ElementType::Diamond => (width / 2.0).round() - BOUND_TEXT_PADDING * 2.0,
+        _ => width - BOUND_TEXT_PADDING * 2.0,
+    }
+}
+
+fn get_bound_text_max_height(container: &Element) -> f64 {
+    let height = container.height;
+    match container.element_type {
+        ElementType::Ellipse => {
+            (height / 2.0 * std::f64::consts::SQRT_2).round() - BOUND_TEXT_PADDING * 2.0
+        }
+        ElementType::Diamond => (height / 2.0).round() - BOUND_TEXT_PADDING * 2.0,
+        _ => height - BOUND_TEXT_PADDING * 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bound_text_position_in_rectangle() {
+        let container = Element {
+            element_type: ElementType::Rectangle,
+            x: 100.0,
+            y: 100.0,
+            width: 200.0,
+            height: 100.0,
+            ..Default::default()
+        };
+        let text = Element {
+            element_type: ElementType::Text,
+            width: 50.0,
+            height: 20.0,
+            text_align: Some(TextAlign::Center),
+            vertical_align: Some(VerticalAlign::Middle),
+            ..Default::default()
+        };
+        assert_eq!(get_bound_text_position(&text, &container), (175.0, 140.0));
+    }
+
+    #[test]
+    fn test_bound_text_position_on_arrow() {
+        let arrow = Element {
+            element_type: ElementType::Arrow,
+            x: 10.0,
+            y: 10.0,
+            points: Some(vec![Point::new(0.0, 0.0), Point::new(100.0, 0.0)]),
+            ..Default::default()
+        };
+        let text = Element {
+            element_type: ElementType::Text,
+            width: 20.0,
+            height: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(get_bound_text_position(&text, &arrow), (50.0, 5.0));
+    }
+}
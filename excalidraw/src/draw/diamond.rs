@@ -1,16 +1,20 @@
-use crate::{draw::utils::default_options_generator, element::Element};
+use crate::{draw::utils::default_options_generator, element::Element, error::Result};
 use log::debug;
-use piet::{kurbo, RenderContext};
+use piet::RenderContext;
 use rough_piet::KurboGenerator;
 use roughr::{core::OptionsBuilder, Point2D};
 
-use super::{utils::get_corner_radius, DrawConfig};
+use super::{
+    utils::{build_options, get_corner_radius, get_element_transform},
+    DrawConfig,
+};
 
-pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) -> Result<()> {
     let mut options = OptionsBuilder::default();
-    let options = default_options_generator(element, element.roundness.is_some(), &mut options)
-        .build()
-        .unwrap();
+    let options = build_options(
+        default_options_generator(element, element.roundness.is_some(), &mut options, config),
+        element,
+    )?;
     let generator = KurboGenerator::new(options);
     let (top_x, top_y, right_x, right_y, bottom_x, bottom_y, left_x, left_y) =
         get_diamond_points(element);
@@ -55,7 +59,7 @@ pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig
                 top_x + vertical_radius,
                 top_y + horizontal_radius,
             );
-            generator.path::<f32>(path)
+            generator.path::<f64>(path)
         }
         None => {
             let points = [
@@ -65,23 +69,21 @@ pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig
                 Point2D::new(left_x, left_y),
             ];
             debug!("points: {:?}", points);
-            generator.polygon::<f32>(&points)
+            generator.polygon::<f64>(&points)
         }
     };
     let _ = ctx.save();
-    ctx.transform(kurbo::Affine::translate((
-        (element.x + config.offset_x) as f64,
-        (element.y + config.offset_y) as f64,
-    )));
+    ctx.transform(get_element_transform(element, config));
     path.draw(ctx);
     let _ = ctx.restore();
+    Ok(())
 }
 
-fn get_diamond_points(element: &Element) -> (f32, f32, f32, f32, f32, f32, f32, f32) {
-    let top_x = (element.width / 2 as f32).floor() + 1 as f32;
-    let top_y: f32 = 0.0;
+fn get_diamond_points(element: &Element) -> (f64, f64, f64, f64, f64, f64, f64, f64) {
+    let top_x = (element.width / 2.0).floor() + 1.0;
+    let top_y: f64 = 0.0;
     let right_x = element.width;
-    let right_y = (element.height / 2 as f32).floor() + 1 as f32;
+    let right_y = (element.height / 2.0).floor() + 1.0;
     let bottom_x = top_x;
     let bottom_y = element.height;
     let left_x = 0.0;
@@ -1,28 +1,30 @@
-use crate::{draw::utils::default_options_generator, element::Element};
-use piet::{kurbo, RenderContext};
+use crate::{draw::utils::default_options_generator, element::Element, error::Result};
+use piet::RenderContext;
 use rough_piet::KurboGenerator;
 use roughr::core::OptionsBuilder;
 
-use super::DrawConfig;
+use super::{
+    utils::{build_options, get_element_transform},
+    DrawConfig,
+};
 
-pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) -> Result<()> {
     let mut options = OptionsBuilder::default();
-    let options = default_options_generator(element, element.roundness.is_some(), &mut options)
-        .curve_fitting(1.0)
-        .build()
-        .unwrap();
+    let options = build_options(
+        default_options_generator(element, element.roundness.is_some(), &mut options, config)
+            .curve_fitting(1.0),
+        element,
+    )?;
     let generator = KurboGenerator::new(options);
-    let path = generator.ellipse::<f32>(
-        element.width / 2 as f32,
-        element.height / 2 as f32,
+    let path = generator.ellipse::<f64>(
+        element.width / 2.0,
+        element.height / 2.0,
         element.width,
         element.height,
     );
     let _ = ctx.save();
-    ctx.transform(kurbo::Affine::translate((
-        (element.x + config.offset_x) as f64,
-        (element.y + config.offset_y) as f64,
-    )));
+    ctx.transform(get_element_transform(element, config));
     path.draw(ctx);
     let _ = ctx.restore();
+    Ok(())
 }
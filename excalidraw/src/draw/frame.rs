@@ -0,0 +1,81 @@
+use crate::element::{Element, ElementType};
+use log::warn;
+use piet::{
+    kurbo::{Rect, RoundedRect},
+    Color, FontFamily, RenderContext, Text, TextLayout, TextLayoutBuilder,
+};
+
+use super::{
+    utils::{apply_theme, get_element_transform},
+    DrawConfig,
+};
+
+const FRAME_STROKE_COLOR: Color = Color::rgb8(0xbb, 0xbb, 0xbb);
+pub const FRAME_STROKE_WIDTH: f64 = 2.0;
+const FRAME_RADIUS: f64 = 8.0;
+const FRAME_NAME_COLOR: Color = Color::rgb8(0x99, 0x99, 0x99);
+const FRAME_NAME_FONT_SIZE: f64 = 14.0;
+const FRAME_NAME_OFFSET_Y: f64 = 3.0;
+
+/**
+ * frame 名称在 frame 上方占用的高度
+ */
+pub fn get_name_height() -> f64 {
+    FRAME_NAME_OFFSET_Y + FRAME_NAME_FONT_SIZE * 1.25
+}
+
+/**
+ * 将后续绘制裁剪到 frame 范围内，需要在 save/restore 之间调用
+ */
+pub fn clip(ctx: &mut impl RenderContext, frame: &Element, config: &DrawConfig) {
+    let transform = get_element_transform(frame, config);
+    ctx.transform(transform);
+    ctx.clip(Rect::new(0.0, 0.0, frame.width, frame.height));
+    ctx.transform(transform.inverse());
+}
+
+pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+    let _ = ctx.save();
+    ctx.transform(get_element_transform(element, config));
+    ctx.stroke(
+        RoundedRect::new(0.0, 0.0, element.width, element.height, FRAME_RADIUS),
+        &apply_theme(FRAME_STROKE_COLOR, config.theme),
+        FRAME_STROKE_WIDTH,
+    );
+    draw_name(ctx, element, config);
+    let _ = ctx.restore();
+}
+
+/**
+ * frame 名称绘制在 frame 左上角的上方
+ */
+fn draw_name(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+    let name = match &element.name {
+        Some(name) => name.clone(),
+        None if element.element_type == ElementType::MagicFrame => "AI Frame".to_string(),
+        None => "Frame".to_string(),
+    };
+    let layout = match ctx
+        .text()
+        .new_text_layout(name)
+        .font(FontFamily::SANS_SERIF, FRAME_NAME_FONT_SIZE)
+        .text_color(apply_theme(FRAME_NAME_COLOR, config.theme))
+        .build()
+    {
+        Ok(layout) => layout,
+        Err(e) => {
+            warn!("Failed to layout frame name {:?}: {:?}", element.name, e);
+            return;
+        }
+    };
+    let height = layout.size().height;
+    let _ = ctx.save();
+    ctx.clip(Rect::new(
+        0.0,
+        -FRAME_NAME_OFFSET_Y - height,
+        element.width,
+        0.0,
+    ));
+    ctx.draw_text(&layout, (0.0, -FRAME_NAME_OFFSET_Y - height));
+    let _ = ctx.restore();
+}
@@ -0,0 +1,435 @@
+use std::f64::consts::PI;
+
+use crate::{element::Element, point::Point};
+use piet::{kurbo::BezPath, Color, RenderContext};
+
+use super::{
+    utils::{get_color, get_element_transform},
+    DrawConfig,
+};
+
+const RATE_OF_PRESSURE_CHANGE: f64 = 0.275;
+const FIXED_PI: f64 = PI + 0.0001;
+const DEFAULT_PRESSURE: f64 = 0.5;
+
+/**
+ * 与 excalidraw 的 getFreeDrawSvgPath 参数保持一致
+ */
+struct StrokeOptions {
+    size: f64,
+    thinning: f64,
+    smoothing: f64,
+    streamline: f64,
+    simulate_pressure: bool,
+    last: bool,
+}
+
+struct StrokePoint {
+    point: Point,
+    pressure: f64,
+    vector: Point,
+    distance: f64,
+    running_length: f64,
+}
+
+pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+    let color = get_color(&element.stroke_color, element.opacity, config).unwrap_or(Color::BLACK);
+    let path = get_free_draw_path(element);
+    let _ = ctx.save();
+    ctx.transform(get_element_transform(element, config));
+    ctx.fill(path, &color);
+    let _ = ctx.restore();
+}
+
+/**
+ * 生成手绘笔迹的轮廓路径（相对于元素原点）
+ */
+pub fn get_free_draw_path(element: &Element) -> BezPath {
+    let simulate_pressure = element.simulate_pressure.unwrap_or(true);
+    let points: Vec<(Point, f64)> = match &element.points {
+        Some(points) if !points.is_empty() => points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let pressure = if simulate_pressure {
+                    DEFAULT_PRESSURE
+                } else {
+                    element
+                        .pressures
+                        .as_ref()
+                        .and_then(|pressures| pressures.get(index))
+                        .map_or(DEFAULT_PRESSURE, |pressure| *pressure)
+                };
+                (*point, pressure)
+            })
+            .collect(),
+        _ => vec![(Point::default(), DEFAULT_PRESSURE)],
+    };
+    let options = StrokeOptions {
+        size: element.stroke_width * 4.25,
+        thinning: 0.6,
+        smoothing: 0.5,
+        streamline: 0.5,
+        simulate_pressure,
+        last: true,
+    };
+    let stroke_points = get_stroke_points(&points, &options);
+    let outline = get_stroke_outline_points(&stroke_points, &options);
+    get_path_from_stroke(&outline)
+}
+
+/**
+ * 对应 excalidraw 的 getSvgPathFromStroke：用二次贝塞尔曲线连接相邻点的中点
+ */
+fn get_path_from_stroke(points: &[Point]) -> BezPath {
+    let mut path = BezPath::new();
+    let first = match points.first() {
+        Some(first) => *first,
+        None => return path,
+    };
+    path.move_to((first.x, first.y));
+    for (index, point) in points.iter().enumerate() {
+        let next = points.get(index + 1).unwrap_or(&first);
+        let mid = med(*point, *next);
+        path.quad_to((point.x, point.y), (mid.x, mid.y));
+    }
+    path.line_to((first.x, first.y));
+    path.close_path();
+    path
+}
+
+fn get_stroke_points(input: &[(Point, f64)], options: &StrokeOptions) -> Vec<StrokePoint> {
+    let t = 0.15 + (1.0 - options.streamline) * 0.85;
+    let mut pts = input.to_vec();
+    if pts.len() == 2 {
+        let last = pts[1];
+        pts.truncate(1);
+        for i in 1..5 {
+            let point = lrp(pts[0].0, last.0, i as f64 / 4.0);
+            pts.push((point, last.1));
+        }
+    }
+    if pts.len() == 1 {
+        let (point, pressure) = pts[0];
+        pts.push((add(point, Point::new(1.0, 1.0)), pressure));
+    }
+
+    let mut stroke_points = vec![StrokePoint {
+        point: pts[0].0,
+        pressure: pts[0].1,
+        vector: Point::new(1.0, 1.0),
+        distance: 0.0,
+        running_length: 0.0,
+    }];
+    let mut has_reached_minimum_length = false;
+    let mut running_length = 0.0;
+    let max = pts.len() - 1;
+    for (i, (input_point, pressure)) in pts.iter().enumerate().skip(1) {
+        let prev = stroke_points[stroke_points.len() - 1].point;
+        let point = if options.last && i == max {
+            *input_point
+        } else {
+            lrp(prev, *input_point, t)
+        };
+        if prev == point {
+            continue;
+        }
+        let distance = dist(point, prev);
+        running_length += distance;
+        if i < max && !has_reached_minimum_length {
+            if running_length < options.size {
+                continue;
+            }
+            has_reached_minimum_length = true;
+        }
+        stroke_points.push(StrokePoint {
+            point,
+            pressure: *pressure,
+            vector: uni(sub(prev, point)),
+            distance,
+            running_length,
+        });
+    }
+    stroke_points[0].vector = stroke_points
+        .get(1)
+        .map_or(Point::default(), |point| point.vector);
+    stroke_points
+}
+
+fn get_stroke_outline_points(points: &[StrokePoint], options: &StrokeOptions) -> Vec<Point> {
+    let size = options.size;
+    if points.is_empty() || size <= 0.0 {
+        return vec![];
+    }
+    let total_length = points[points.len() - 1].running_length;
+    let min_distance = (size * options.smoothing).powi(2);
+    let mut left_pts: Vec<Point> = vec![];
+    let mut right_pts: Vec<Point> = vec![];
+
+    let mut prev_pressure = points
+        .iter()
+        .take(10)
+        .fold(points[0].pressure, |acc, curr| {
+            let pressure = if options.simulate_pressure {
+                simulate_pressure(acc, curr.distance, size)
+            } else {
+                curr.pressure
+            };
+            (acc + pressure) / 2.0
+        });
+    let mut radius = get_stroke_radius(size, options.thinning, points[points.len() - 1].pressure);
+    let mut first_radius: Option<f64> = None;
+    let mut prev_vector = points[0].vector;
+    let mut pl = points[0].point;
+    let mut pr = pl;
+    let mut tl;
+    let mut tr;
+    let mut is_prev_point_sharp_corner = false;
+
+    for (i, stroke_point) in points.iter().enumerate() {
+        let StrokePoint {
+            point,
+            vector,
+            distance,
+            running_length,
+            ..
+        } = *stroke_point;
+        let mut pressure = stroke_point.pressure;
+        if i < points.len() - 1 && total_length - running_length < 3.0 {
+            continue;
+        }
+        if options.thinning != 0.0 {
+            if options.simulate_pressure {
+                pressure = simulate_pressure(prev_pressure, distance, size);
+            }
+            radius = get_stroke_radius(size, options.thinning, pressure);
+        } else {
+            radius = size / 2.0;
+        }
+        if first_radius.is_none() {
+            first_radius = Some(radius);
+        }
+        radius = radius.max(0.01);
+
+        let next_vector = points.get(i + 1).unwrap_or(stroke_point).vector;
+        let next_dpr = if i < points.len() - 1 {
+            dpr(vector, next_vector)
+        } else {
+            1.0
+        };
+        let prev_dpr = dpr(vector, prev_vector);
+        let is_point_sharp_corner = prev_dpr < 0.0 && !is_prev_point_sharp_corner;
+        let is_next_point_sharp_corner = next_dpr < 0.0;
+
+        if is_point_sharp_corner || is_next_point_sharp_corner {
+            // 急转弯处绘制半圆，避免轮廓自交
+            let offset = mul(per(prev_vector), radius);
+            let step = 1.0 / 13.0;
+            let mut t = 0.0;
+            tl = pl;
+            tr = pr;
+            while t <= 1.0 {
+                tl = rot_around(sub(point, offset), point, FIXED_PI * t);
+                left_pts.push(tl);
+                tr = rot_around(add(point, offset), point, FIXED_PI * -t);
+                right_pts.push(tr);
+                t += step;
+            }
+            pl = tl;
+            pr = tr;
+            if is_next_point_sharp_corner {
+                is_prev_point_sharp_corner = true;
+            }
+            continue;
+        }
+        is_prev_point_sharp_corner = false;
+
+        if i == points.len() - 1 {
+            let offset = mul(per(vector), radius);
+            left_pts.push(sub(point, offset));
+            right_pts.push(add(point, offset));
+            continue;
+        }
+
+        let offset = mul(per(lrp(next_vector, vector, next_dpr)), radius);
+        tl = sub(point, offset);
+        if i <= 1 || dist2(pl, tl) > min_distance {
+            left_pts.push(tl);
+            pl = tl;
+        }
+        tr = add(point, offset);
+        if i <= 1 || dist2(pr, tr) > min_distance {
+            right_pts.push(tr);
+            pr = tr;
+        }
+        prev_pressure = pressure;
+        prev_vector = vector;
+    }
+
+    let first_point = points[0].point;
+    let last_point = if points.len() > 1 {
+        points[points.len() - 1].point
+    } else {
+        add(points[0].point, Point::new(1.0, 1.0))
+    };
+
+    if points.len() == 1 {
+        // 只有一个点时画一个圆点
+        let start = prj(
+            first_point,
+            uni(per(sub(first_point, last_point))),
+            -first_radius.unwrap_or(radius),
+        );
+        let step = 1.0 / 13.0;
+        let mut dot_pts = vec![];
+        let mut t = step;
+        while t <= 1.0 {
+            dot_pts.push(rot_around(start, first_point, FIXED_PI * 2.0 * t));
+            t += step;
+        }
+        return dot_pts;
+    }
+
+    let mut start_cap = vec![];
+    if let Some(first_right) = right_pts.first() {
+        let step = 1.0 / 13.0;
+        let mut t = step;
+        while t <= 1.0 {
+            start_cap.push(rot_around(*first_right, first_point, FIXED_PI * t));
+            t += step;
+        }
+    }
+
+    let mut end_cap = vec![];
+    let direction = per(neg(points[points.len() - 1].vector));
+    let start = prj(last_point, direction, radius);
+    let step = 1.0 / 29.0;
+    let mut t = step;
+    while t < 1.0 {
+        end_cap.push(rot_around(start, last_point, FIXED_PI * 3.0 * t));
+        t += step;
+    }
+
+    right_pts.reverse();
+    left_pts
+        .into_iter()
+        .chain(end_cap)
+        .chain(right_pts)
+        .chain(start_cap)
+        .collect()
+}
+
+fn simulate_pressure(prev_pressure: f64, distance: f64, size: f64) -> f64 {
+    let sp = (distance / size).min(1.0);
+    let rp = (1.0 - sp).min(1.0);
+    (prev_pressure + (rp - prev_pressure) * (sp * RATE_OF_PRESSURE_CHANGE)).min(1.0)
+}
+
+/**
+ * excalidraw 使用 easing: t => sin(t * PI / 2)
+ */
+fn get_stroke_radius(size: f64, thinning: f64, pressure: f64) -> f64 {
+    let t = 0.5 - thinning * (0.5 - pressure);
+    size * (t * PI / 2.0).sin()
+}
+
+fn add(a: Point, b: Point) -> Point {
+    Point::new(a.x + b.x, a.y + b.y)
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    Point::new(a.x - b.x, a.y - b.y)
+}
+
+fn mul(a: Point, n: f64) -> Point {
+    Point::new(a.x * n, a.y * n)
+}
+
+fn neg(a: Point) -> Point {
+    Point::new(-a.x, -a.y)
+}
+
+fn per(a: Point) -> Point {
+    Point::new(a.y, -a.x)
+}
+
+fn dpr(a: Point, b: Point) -> f64 {
+    a.x * b.x + a.y * b.y
+}
+
+fn dist2(a: Point, b: Point) -> f64 {
+    (a.x - b.x).powi(2) + (a.y - b.y).powi(2)
+}
+
+fn dist(a: Point, b: Point) -> f64 {
+    dist2(a, b).sqrt()
+}
+
+fn uni(a: Point) -> Point {
+    let length = a.x.hypot(a.y);
+    if length == 0.0 {
+        return a;
+    }
+    mul(a, 1.0 / length)
+}
+
+fn lrp(a: Point, b: Point, t: f64) -> Point {
+    add(a, mul(sub(b, a), t))
+}
+
+fn prj(a: Point, b: Point, c: f64) -> Point {
+    add(a, mul(b, c))
+}
+
+fn med(a: Point, b: Point) -> Point {
+    lrp(a, b, 0.5)
+}
+
+fn rot_around(a: Point, c: Point, r: f64) -> Point {
+    let (s, co) = r.sin_cos();
+    let px = a.x - c.x;
+    let py = a.y - c.y;
+    Point::new(px * co - py * s + c.x, px * s + py * co + c.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element::ElementType;
+
+    #[test]
+    fn test_free_draw_path_is_closed_outline() {
+        let element = Element {
+            element_type: ElementType::Freedraw,
+            stroke_width: 1.0,
+            points: Some(vec![
+                Point::new(0.0, 0.0),
+                Point::new(10.0, 5.0),
+                Point::new(30.0, 10.0),
+                Point::new(60.0, 12.0),
+            ]),
+            simulate_pressure: Some(true),
+            ..Default::default()
+        };
+        let path = get_free_draw_path(&element);
+        assert!(path.elements().len() > 4);
+        assert_eq!(
+            path.elements().last(),
+            Some(&piet::kurbo::PathEl::ClosePath)
+        );
+    }
+
+    #[test]
+    fn test_free_draw_single_point_is_dot() {
+        let element = Element {
+            element_type: ElementType::Freedraw,
+            stroke_width: 2.0,
+            points: Some(vec![Point::new(0.0, 0.0)]),
+            pressures: Some(vec![0.5]),
+            simulate_pressure: Some(false),
+            ..Default::default()
+        };
+        let path = get_free_draw_path(&element);
+        assert!(!path.elements().is_empty());
+    }
+}
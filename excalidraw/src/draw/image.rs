@@ -0,0 +1,90 @@
+use crate::{element::Element, files::BinaryFiles};
+use log::warn;
+use piet::{
+    kurbo::{self, Rect},
+    Color, ImageFormat, InterpolationMode, RenderContext,
+};
+
+use super::{utils::get_element_transform, DrawConfig};
+
+pub fn draw(
+    ctx: &mut impl RenderContext,
+    element: &Element,
+    files: &BinaryFiles,
+    config: &DrawConfig,
+) {
+    let width = element.width;
+    let height = element.height;
+    let _ = ctx.save();
+    ctx.transform(get_element_transform(element, config));
+    // scale 为负数时表示图片被翻转，绕中心点镜像
+    let (scale_x, scale_y) = element.scale.unwrap_or((1.0, 1.0));
+    ctx.transform(
+        kurbo::Affine::translate((width / 2.0, height / 2.0))
+            * kurbo::Affine::scale_non_uniform(scale_x.signum(), scale_y.signum())
+            * kurbo::Affine::translate((-width / 2.0, -height / 2.0)),
+    );
+    let dst_rect = Rect::new(0.0, 0.0, width, height);
+
+    let file = element.file_id.as_deref().and_then(|id| files.get(id));
+    let decoded = match file.map(|file| file.decode()) {
+        Some(Ok(decoded)) => decoded,
+        Some(Err(e)) => {
+            warn!("Failed to decode image {:?}: {:?}", element.file_id, e);
+            draw_placeholder(ctx, dst_rect);
+            let _ = ctx.restore();
+            return;
+        }
+        None => {
+            draw_placeholder(ctx, dst_rect);
+            let _ = ctx.restore();
+            return;
+        }
+    };
+
+    let mut pixels = decoded.pixels;
+    if element.opacity < 100 {
+        let opacity = element.opacity as u16;
+        pixels
+            .iter_mut()
+            .for_each(|channel| *channel = (*channel as u16 * opacity / 100) as u8);
+    }
+    let image = match ctx.make_image(
+        decoded.width,
+        decoded.height,
+        &pixels,
+        ImageFormat::RgbaPremul,
+    ) {
+        Ok(image) => image,
+        Err(e) => {
+            warn!("Failed to create image {:?}: {:?}", element.file_id, e);
+            let _ = ctx.restore();
+            return;
+        }
+    };
+
+    match &element.crop {
+        Some(crop) => {
+            // crop 以图片原始尺寸为坐标系，换算到解码后的像素尺寸
+            let ratio_x = decoded.width as f64 / crop.natural_width.max(1.0);
+            let ratio_y = decoded.height as f64 / crop.natural_height.max(1.0);
+            let src_rect = Rect::new(
+                crop.x * ratio_x,
+                crop.y * ratio_y,
+                (crop.x + crop.width) * ratio_x,
+                (crop.y + crop.height) * ratio_y,
+            );
+            ctx.draw_image_area(&image, src_rect, dst_rect, InterpolationMode::Bilinear);
+        }
+        None => ctx.draw_image(&image, dst_rect, InterpolationMode::Bilinear),
+    }
+    let _ = ctx.restore();
+}
+
+/**
+ * 图片文件缺失或无法解码时绘制占位框
+ */
+fn draw_placeholder(ctx: &mut impl RenderContext, rect: Rect) {
+    ctx.fill(rect, &Color::rgb8(0xe9, 0xec, 0xef));
+    ctx.stroke(rect, &Color::rgb8(0xad, 0xb5, 0xbd), 1.0);
+}
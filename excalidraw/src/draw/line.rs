@@ -1,27 +1,33 @@
-use crate::{draw::utils::default_options_generator, element::Element, point::Point};
+use crate::{
+    draw::utils::default_options_generator, element::Element, error::Result, point::Point,
+};
 use palette::Srgba;
-use piet::{kurbo, RenderContext};
+use piet::RenderContext;
 use rough_piet::KurboGenerator;
 use roughr::core::OptionsBuilder;
 
 use super::{
-    utils::{get_points2d, is_path_loop},
+    utils::{build_options, get_element_transform, get_points2d, is_path_loop},
     DrawConfig,
 };
 
-pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) -> Result<()> {
     let mut options = OptionsBuilder::default();
-    let options = default_options_generator(element, element.roundness.is_some(), &mut options);
+    let options =
+        default_options_generator(element, element.roundness.is_some(), &mut options, config);
 
     let default_points = vec![Point::default(), Point::default()];
     let points = match &element.points {
         Some(points) => points,
         None => &default_points,
     };
+    if points.len() < 2 {
+        return Ok(());
+    }
     if !is_path_loop(points) {
         options.fill(Srgba::new(0.0, 0.0, 0.0, 0.0));
     }
-    let generator = KurboGenerator::new(options.build().unwrap());
+    let generator = KurboGenerator::new(build_options(options, element)?);
     let p = get_points2d(points);
 
     let shape = if element.roundness.is_none() {
@@ -30,10 +36,8 @@ pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig
         generator.curve(&p[..])
     };
     let _ = ctx.save();
-    ctx.transform(kurbo::Affine::translate((
-        (element.x + config.offset_x) as f64,
-        (element.y + config.offset_y) as f64,
-    )));
+    ctx.transform(get_element_transform(element, config));
     shape.draw(ctx);
     let _ = ctx.restore();
+    Ok(())
 }
@@ -1,32 +1,132 @@
 mod arrow;
+mod bound_text;
 mod diamond;
 mod ellipse;
+mod frame;
+mod freedraw;
+mod image;
 mod line;
 mod rectangle;
+mod text;
 mod utils;
-use crate::element::{Element, ElementType};
+use crate::{
+    element::{Element, ElementType},
+    error::{Error, Result},
+    files::BinaryFiles,
+};
+pub(crate) use arrow::get_arrowhead_bounds;
+use bound_text::get_bound_text;
+pub(crate) use bound_text::{get_bound_text_position, get_container};
+pub(crate) use frame::{get_name_height, FRAME_STROKE_WIDTH};
+pub(crate) use freedraw::get_free_draw_path;
 use piet::RenderContext;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+pub(crate) use utils::{apply_theme, color_from_hex, get_transform};
+
+/**
+ * 绘制主题，深色模式与 excalidraw 的 exportWithDarkMode 一致
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
 
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DrawConfig {
-    pub offset_x: f32,
-    pub offset_y: f32,
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub background_color: String,
+    pub theme: Theme,
 }
 
-pub fn draw(ctx: &mut impl RenderContext, elements: &Vec<Element>, config: &DrawConfig) {
+/**
+ * 绘制所有元素，出错的元素会被跳过，其余元素照常绘制，返回被跳过元素的错误
+ */
+pub fn draw(
+    ctx: &mut impl RenderContext,
+    elements: &Vec<Element>,
+    files: &BinaryFiles,
+    config: &DrawConfig,
+) -> Vec<Error> {
+    let element_map: HashMap<&str, &Element> = elements
+        .iter()
+        .map(|element| (element.id.as_str(), element))
+        .collect();
+    let mut errors = vec![];
     for element in elements {
         if element.is_deleted {
             continue;
         }
-        match element.element_type {
-            ElementType::Rectangle => rectangle::draw(ctx, element, config),
-            ElementType::Diamond => diamond::draw(ctx, element, config),
-            ElementType::Ellipse => ellipse::draw(ctx, element, config),
-            ElementType::Arrow => arrow::draw(ctx, element, config),
-            ElementType::Line => line::draw(ctx, element, config),
-            _ => {}
+        // frame 内的元素随 frame 一起绘制
+        if get_frame(element, &element_map).is_some() {
+            continue;
+        }
+        if element.is_frame() {
+            if let Err(e) = element.validate() {
+                errors.push(e);
+                continue;
+            }
+            let _ = ctx.save();
+            frame::clip(ctx, element, config);
+            elements
+                .iter()
+                .filter(|child| !child.is_deleted && child.frame_id.as_deref() == Some(&element.id))
+                .filter_map(|child| draw_element(ctx, child, &element_map, files, config).err())
+                .for_each(|e| errors.push(e));
+            let _ = ctx.restore();
+            frame::draw(ctx, element, config);
+            continue;
+        }
+        if let Err(e) = draw_element(ctx, element, &element_map, files, config) {
+            errors.push(e);
         }
     }
+    errors
+}
+
+fn draw_element(
+    ctx: &mut impl RenderContext,
+    element: &Element,
+    element_map: &HashMap<&str, &Element>,
+    files: &BinaryFiles,
+    config: &DrawConfig,
+) -> Result<()> {
+    element.validate()?;
+    match element.element_type {
+        ElementType::Rectangle => rectangle::draw(ctx, element, config)?,
+        ElementType::Diamond => diamond::draw(ctx, element, config)?,
+        ElementType::Ellipse => ellipse::draw(ctx, element, config)?,
+        ElementType::Arrow => {
+            let label = get_bound_text(element, element_map);
+            arrow::draw(ctx, element, label, config)?
+        }
+        ElementType::Line => line::draw(ctx, element, config)?,
+        ElementType::Freedraw => freedraw::draw(ctx, element, config),
+        ElementType::Image => image::draw(ctx, element, files, config),
+        ElementType::Text => {
+            let container = get_container(element, element_map);
+            text::draw(ctx, element, container, config)
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/**
+ * 获取元素所属的 frame
+ */
+fn get_frame<'a>(
+    element: &Element,
+    element_map: &HashMap<&str, &'a Element>,
+) -> Option<&'a Element> {
+    let frame_id = element.frame_id.as_deref()?;
+    element_map
+        .get(frame_id)
+        .copied()
+        .filter(|frame| !frame.is_deleted && frame.is_frame() && frame.validate().is_ok())
 }
@@ -1,15 +1,19 @@
-use crate::{draw::utils::default_options_generator, element::Element};
-use piet::{kurbo, RenderContext};
+use crate::{draw::utils::default_options_generator, element::Element, error::Result};
+use piet::RenderContext;
 use rough_piet::KurboGenerator;
 use roughr::core::OptionsBuilder;
 
-use super::{utils::get_corner_radius, DrawConfig};
+use super::{
+    utils::{build_options, get_corner_radius, get_element_transform},
+    DrawConfig,
+};
 
-pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) {
+pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig) -> Result<()> {
     let mut options = OptionsBuilder::default();
-    let options = default_options_generator(element, element.roundness.is_some(), &mut options)
-        .build()
-        .unwrap();
+    let options = build_options(
+        default_options_generator(element, element.roundness.is_some(), &mut options, config),
+        element,
+    )?;
     let generator = KurboGenerator::new(options);
     let path = match &element.roundness {
         Some(roundness) => {
@@ -23,15 +27,13 @@ pub fn draw(ctx: &mut impl RenderContext, element: &Element, config: &DrawConfig
               w,
               w,r,w,h - r,w,h,w - r,h,r,h,h,h - r,r,r
           );
-            generator.path::<f32>(path)
+            generator.path::<f64>(path)
         }
-        None => generator.rectangle::<f32>(0.0, 0.0, element.width, element.height),
+        None => generator.rectangle::<f64>(0.0, 0.0, element.width, element.height),
     };
     let _ = ctx.save();
-    ctx.transform(kurbo::Affine::translate((
-        (element.x + config.offset_x) as f64,
-        (element.y + config.offset_y) as f64,
-    )));
+    ctx.transform(get_element_transform(element, config));
     path.draw(ctx);
     let _ = ctx.restore();
+    Ok(())
 }
@@ -0,0 +1,72 @@
+use crate::element::{Element, TextAlign, DEFAULT_FONT_SIZE};
+use log::warn;
+use piet::{Color, RenderContext, Text, TextLayout, TextLayoutBuilder};
+
+use super::{
+    bound_text::get_bound_text_position,
+    utils::{get_color, get_transform},
+    DrawConfig,
+};
+
+pub fn draw(
+    ctx: &mut impl RenderContext,
+    element: &Element,
+    container: Option<&Element>,
+    config: &DrawConfig,
+) {
+    let text = match &element.text {
+        Some(text) => text,
+        None => return,
+    };
+    let font_size = element.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+    let line_height = element.get_line_height_in_px();
+    let text_align = element.text_align.unwrap_or_default();
+    let color = get_color(&element.stroke_color, element.opacity, config).unwrap_or(Color::BLACK);
+    let font_family = element.font_family.unwrap_or_default();
+    let family = ctx
+        .text()
+        .font_family(font_family.font_name())
+        .unwrap_or_else(|| font_family.fallback());
+
+    let (x, y) = match container {
+        Some(container) => get_bound_text_position(element, container),
+        None => (element.x, element.y),
+    };
+
+    let _ = ctx.save();
+    ctx.transform(get_transform(
+        x,
+        y,
+        (element.width / 2.0, element.height / 2.0),
+        element.angle,
+        config,
+    ));
+    for (index, line) in text.replace("\r\n", "\n").split('\n').enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let layout = match ctx
+            .text()
+            .new_text_layout(line.to_string())
+            .font(family.clone(), font_size)
+            .text_color(color)
+            .build()
+        {
+            Ok(layout) => layout,
+            Err(e) => {
+                warn!("Failed to layout text {:?}: {:?}", line, e);
+                continue;
+            }
+        };
+        let size = layout.size();
+        let x = match text_align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (element.width - size.width) / 2.0,
+            TextAlign::Right => element.width - size.width,
+        };
+        // 按 lineHeight 排布每一行，字形在行内垂直居中
+        let y = index as f64 * line_height + (line_height - size.height) / 2.0;
+        ctx.draw_text(&layout, (x, y));
+    }
+    let _ = ctx.restore();
+}
@@ -1,37 +1,42 @@
 use crate::{
     element::{Element, Roundness, RoundnessType, StrokeStyle},
+    error::{Error, Result},
     point::Point,
 };
 use euclid::{Point2D, UnknownUnit};
 use log::debug;
 use palette::Srgba;
-use roughr::core::OptionsBuilder;
+use piet::{
+    kurbo::{Affine, Rect},
+    Color,
+};
+
+use super::{DrawConfig, Theme};
+use roughr::core::{Options, OptionsBuilder};
 
 pub fn default_options_generator<'a, 'b>(
     element: &'a Element,
     continuous_path: bool,
     options: &'b mut OptionsBuilder,
+    config: &DrawConfig,
 ) -> &'b mut OptionsBuilder {
     let default_color = Srgba::new(0.0, 0.0, 0.0, 0.0);
     let stroke_color =
-        srgba_from_hex(&element.stroke_color, element.opacity).unwrap_or(default_color);
+        get_srgba(&element.stroke_color, element.opacity, config).unwrap_or(default_color);
     let fill_color =
-        srgba_from_hex(&element.background_color, element.opacity).unwrap_or(default_color);
+        get_srgba(&element.background_color, element.opacity, config).unwrap_or(default_color);
     options
         .seed(element.seed)
         .fill_style(element.fill_style.into_roughr())
-        .stroke_width(get_stroke_width(
-            &element.stroke_style,
-            element.stroke_width,
-        ))
+        .stroke_width(get_stroke_width(&element.stroke_style, element.stroke_width) as f32)
         .stroke_line_dash(stroke_line_dash(
             &element.stroke_style,
             element.stroke_width,
         ))
-        .fill_weight(element.stroke_width / 2 as f32)
-        .hachure_gap(element.stroke_width * 4 as f32)
+        .fill_weight((element.stroke_width / 2.0) as f32)
+        .hachure_gap((element.stroke_width * 4.0) as f32)
         .disable_multi_stroke(element.stroke_style != StrokeStyle::Solid)
-        .roughness(element.roughness)
+        .roughness(element.roughness as f32)
         .stroke(stroke_color)
         .fill(fill_color)
         .preserve_vertices(continuous_path)
@@ -41,12 +46,46 @@ pub fn default_options_generator<'a, 'b>(
     options
 }
 
+/**
+ * 构建 rough 配置，失败时转换为绘制错误
+ */
+pub fn build_options(options: &OptionsBuilder, element: &Element) -> Result<Options> {
+    options.build().map_err(|e| Error::render(&element.id, e))
+}
+
+/**
+ * 将元素平移到画布位置，并绕中心点旋转 angle
+ */
+pub fn get_transform(
+    x: f64,
+    y: f64,
+    center: (f64, f64),
+    angle: f64,
+    config: &DrawConfig,
+) -> Affine {
+    let (cx, cy) = center;
+    Affine::translate((x + config.offset_x, y + config.offset_y))
+        * Affine::translate((cx, cy))
+        * Affine::rotate(angle)
+        * Affine::translate((-cx, -cy))
+}
+
+pub fn get_element_transform(element: &Element, config: &DrawConfig) -> Affine {
+    get_transform(
+        element.x,
+        element.y,
+        element.get_center(),
+        element.angle,
+        config,
+    )
+}
+
 pub fn srgba_from_hex(hex: &str, opacity: u8) -> Option<Srgba> {
     // Remove the leading '#' if it exists
     let hex = hex.trim_start_matches('#');
 
-    // Check the length of our string
-    if hex.len() != 6 && hex.len() != 8 {
+    // Check the length of our string, non-ASCII input would be sliced inside a character
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
         return None;
     }
 
@@ -67,24 +106,59 @@ pub fn srgba_from_hex(hex: &str, opacity: u8) -> Option<Srgba> {
     Some(Srgba::new(r, g, b, a).into_format())
 }
 
-pub fn stroke_line_dash(stroke_style: &StrokeStyle, stroke_width: f32) -> Vec<f64> {
+pub fn color_from_hex(hex: &str, opacity: u8) -> Option<Color> {
+    let (r, g, b, a) = srgba_from_hex(hex, opacity)?.into_components();
+    Some(Color::rgba(r as f64, g as f64, b as f64, a as f64))
+}
+
+/**
+ * 解析元素颜色并按 config 的主题转换
+ */
+pub fn get_srgba(hex: &str, opacity: u8, config: &DrawConfig) -> Option<Srgba> {
+    let (r, g, b, a) = get_color(hex, opacity, config)?.as_rgba();
+    Some(Srgba::new(r as f32, g as f32, b as f32, a as f32))
+}
+
+pub fn get_color(hex: &str, opacity: u8, config: &DrawConfig) -> Option<Color> {
+    Some(apply_theme(color_from_hex(hex, opacity)?, config.theme))
+}
+
+/**
+ * 深色模式使用 excalidraw 的 `invert(93%) hue-rotate(180deg)` 滤镜，在 sRGB 空间计算
+ */
+pub fn apply_theme(color: Color, theme: Theme) -> Color {
+    if theme == Theme::Light {
+        return color;
+    }
+    let (r, g, b, a) = color.as_rgba();
+    let invert = |c: f64| 0.93 - 0.86 * c;
+    let (r, g, b) = (invert(r), invert(g), invert(b));
+    Color::rgba(
+        (-0.574 * r + 1.430 * g + 0.144 * b).clamp(0.0, 1.0),
+        (0.426 * r + 0.430 * g + 0.144 * b).clamp(0.0, 1.0),
+        (0.426 * r + 1.430 * g - 0.856 * b).clamp(0.0, 1.0),
+        a,
+    )
+}
+
+pub fn stroke_line_dash(stroke_style: &StrokeStyle, stroke_width: f64) -> Vec<f64> {
     debug!("stroke_style: {:?}", stroke_style);
     match stroke_style {
         StrokeStyle::Solid => vec![],
-        StrokeStyle::Dashed => vec![8 as f64, 8 as f64 + stroke_width as f64],
-        StrokeStyle::Dotted => vec![1.5 as f64, 6 as f64 + stroke_width as f64],
+        StrokeStyle::Dashed => vec![8.0, 8.0 + stroke_width],
+        StrokeStyle::Dotted => vec![1.5, 6.0 + stroke_width],
     }
 }
 
-pub fn get_stroke_width(stroke_style: &StrokeStyle, stroke_width: f32) -> f32 {
+pub fn get_stroke_width(stroke_style: &StrokeStyle, stroke_width: f64) -> f64 {
     if stroke_style == &StrokeStyle::Solid {
         stroke_width
     } else {
-        stroke_width + 0.5 as f32
+        stroke_width + 0.5
     }
 }
 
-pub fn get_corner_radius(x: f32, roundness: &Roundness) -> f32 {
+pub fn get_corner_radius(x: f64, roundness: &Roundness) -> f64 {
     let default_proportional_radius = 0.25;
     match roundness.type_field {
         RoundnessType::Legacy => x * default_proportional_radius,
@@ -108,6 +182,18 @@ pub fn get_points2d(points: &Vec<Point>) -> Vec<Point2D<f64, UnknownUnit>> {
         .collect()
 }
 
+pub fn get_points_bounds(points: &[Point]) -> Rect {
+    let mut points = points.iter();
+    let first = match points.next() {
+        Some(point) => point,
+        None => return Rect::ZERO,
+    };
+    points.fold(
+        Rect::new(first.x, first.y, first.x, first.y),
+        |rect, point| rect.union_pt((point.x, point.y)),
+    )
+}
+
 pub fn is_path_loop(points: &Vec<Point>) -> bool {
     if points.len() >= 3 {
         let first = points.first().unwrap();
@@ -135,3 +221,19 @@ where
 {
     ((a * a + b * b).into()).sqrt()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srgba_from_hex() {
+        assert_eq!(
+            srgba_from_hex("#ff000080", 100).map(|c| c.into_format::<u8, u8>().into_components()),
+            Some((255, 0, 0, 128))
+        );
+        assert!(srgba_from_hex("#aéaaa", 100).is_none());
+        assert!(srgba_from_hex("#ééé", 100).is_none());
+        assert!(color_from_hex("transparent", 100).is_none());
+    }
+}
@@ -1,11 +1,10 @@
-use log::info;
-use palette::Srgba;
-use piet::RenderContext;
-use rough_piet::KurboGenerator;
-use roughr::core::OptionsBuilder;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
-use crate::point::Point;
+use crate::{
+    error::{self, Error},
+    point::Point,
+};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -46,6 +45,11 @@ pub enum ElementType {
     Arrow,
     Line,
     Text,
+    Freedraw,
+    Image,
+    Frame,
+    #[serde(rename = "magicframe")]
+    MagicFrame,
     Selection,
 }
 
@@ -68,13 +72,27 @@ impl Default for ElementType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RoundnessType {
     Legacy,
     ProportionalRadius,
     AdaptiveRadius,
 }
 
+impl Serialize for RoundnessType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let i: u8 = match self {
+            RoundnessType::Legacy => 1,
+            RoundnessType::ProportionalRadius => 2,
+            RoundnessType::AdaptiveRadius => 3,
+        };
+        serializer.serialize_u8(i)
+    }
+}
+
 impl<'de> Deserialize<'de> for RoundnessType {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -94,16 +112,184 @@ impl<'de> Deserialize<'de> for RoundnessType {
 pub struct Roundness {
     #[serde(rename = "type")]
     pub type_field: RoundnessType,
-    pub value: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "snake_case")]
 pub enum Arrowhead {
     Arrow,
     Bar,
     Dot,
+    Circle,
+    CircleOutline,
     Triangle,
+    TriangleOutline,
+    Diamond,
+    DiamondOutline,
+    CrowfootOne,
+    CrowfootMany,
+    CrowfootOneOrMany,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontFamily {
+    #[default]
+    Virgil,
+    Helvetica,
+    Cascadia,
+    Excalifont,
+    Nunito,
+    LilitaOne,
+    ComicShanns,
+    LiberationSans,
+    LocalFont,
+    Assistant,
+    /**
+     * 未知的字体 id，保留原值以便原样写回，绘制时回退到默认字体
+     */
+    Unknown(u8),
+}
+
+impl FontFamily {
+    pub fn font_name(&self) -> &'static str {
+        match self {
+            Self::Virgil => "Virgil",
+            Self::Helvetica => "Helvetica",
+            Self::Cascadia => "Cascadia",
+            Self::Excalifont => "Excalifont",
+            Self::Nunito => "Nunito",
+            Self::LilitaOne => "Lilita One",
+            Self::ComicShanns => "Comic Shanns",
+            Self::LiberationSans => "Liberation Sans",
+            Self::LocalFont => "Local Font",
+            Self::Assistant => "Assistant",
+            Self::Unknown(_) => Self::default().font_name(),
+        }
+    }
+
+    /**
+     * 字体不存在时使用的系统字体
+     */
+    pub fn fallback(&self) -> piet::FontFamily {
+        match self {
+            Self::Cascadia | Self::ComicShanns => piet::FontFamily::MONOSPACE,
+            _ => piet::FontFamily::SANS_SERIF,
+        }
+    }
+
+    /**
+     * 与 excalidraw 的 getDefaultLineHeight 保持一致
+     */
+    pub fn default_line_height(&self) -> f64 {
+        match self {
+            Self::Helvetica => 1.15,
+            Self::Cascadia => 1.2,
+            _ => 1.25,
+        }
+    }
+}
+
+impl Serialize for FontFamily {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let i: u8 = match self {
+            FontFamily::Virgil => 1,
+            FontFamily::Helvetica => 2,
+            FontFamily::Cascadia => 3,
+            FontFamily::Excalifont => 5,
+            FontFamily::Nunito => 6,
+            FontFamily::LilitaOne => 7,
+            FontFamily::ComicShanns => 8,
+            FontFamily::LiberationSans => 9,
+            FontFamily::LocalFont => 4,
+            FontFamily::Assistant => 10,
+            FontFamily::Unknown(i) => *i,
+        };
+        serializer.serialize_u8(i)
+    }
+}
+
+impl<'de> Deserialize<'de> for FontFamily {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let i = u8::deserialize(deserializer)?;
+        match i {
+            1 => Ok(FontFamily::Virgil),
+            2 => Ok(FontFamily::Helvetica),
+            3 => Ok(FontFamily::Cascadia),
+            5 => Ok(FontFamily::Excalifont),
+            6 => Ok(FontFamily::Nunito),
+            7 => Ok(FontFamily::LilitaOne),
+            8 => Ok(FontFamily::ComicShanns),
+            4 => Ok(FontFamily::LocalFont),
+            9 => Ok(FontFamily::LiberationSans),
+            10 => Ok(FontFamily::Assistant),
+            i => Ok(FontFamily::Unknown(i)),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageStatus {
+    Pending,
+    Saved,
+    Error,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageCrop {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub natural_width: f64,
+    pub natural_height: f64,
+}
+
+pub const DEFAULT_FONT_SIZE: f64 = 20.0;
+
+/**
+ * 肘形箭头中被用户固定的线段，index 为线段终点在 points 中的下标
+ */
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixedSegment {
+    pub start: Point,
+    pub end: Point,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundElement {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_field: ElementType,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -112,17 +298,17 @@ pub struct Element {
     pub id: String,
     #[serde(rename = "type")]
     pub element_type: ElementType,
-    pub x: f32,
-    pub y: f32,
-    pub width: f32,
-    pub height: f32,
-    pub angle: f32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub angle: f64,
     pub stroke_color: String,
     pub background_color: String,
     pub fill_style: FillStyle,
-    pub stroke_width: f32,
+    pub stroke_width: f64,
     pub stroke_style: StrokeStyle,
-    pub roughness: f32,
+    pub roughness: f64,
     pub opacity: u8,
     pub seed: u64,
     pub version: i64,
@@ -131,30 +317,151 @@ pub struct Element {
     pub updated: i64,
     pub locked: bool,
     pub roundness: Option<Roundness>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub points: Option<Vec<Point>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub end_arrowhead: Option<Arrowhead>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub start_arrowhead: Option<Arrowhead>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elbowed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed_segments: Option<Vec<FixedSegment>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<FontFamily>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_align: Option<TextAlign>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertical_align: Option<VerticalAlign>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_height: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bound_elements: Option<Vec<BoundElement>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressures: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulate_pressure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ImageStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<(f64, f64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<ImageCrop>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /**
+     * 未建模的字段（groupIds、link、customData 等），序列化时原样写回
+     */
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl Element {
-    pub fn draw(&self, ctx: &mut impl RenderContext) {
-        info!("Drawing element: {:?}", self);
-        let options = OptionsBuilder::default()
-            .stroke(Srgba::from_components((114u8, 87u8, 82u8, 255u8)).into_format())
-            .fill(Srgba::from_components((254u8, 246u8, 201u8, 255u8)).into_format())
-            .fill_style(FillStyle::Hachure.into_roughr())
-            .fill_weight(self.stroke_width)
-            .build()
-            .unwrap();
-        let generator = KurboGenerator::new(options);
-        let circle_paths =
-            generator.rectangle::<f32>(100 as f32, 100 as f32, 100 as f32, 100 as f32);
-        circle_paths.draw(ctx);
-    }
-
-    pub fn get_size(&self) -> (f32, f32) {
+    pub fn get_size(&self) -> (f64, f64) {
         (self.width, self.height)
     }
+
+    pub fn is_frame(&self) -> bool {
+        matches!(
+            self.element_type,
+            ElementType::Frame | ElementType::MagicFrame
+        )
+    }
+
+    /**
+     * 检查元素是否可以绘制：坐标、尺寸等数值必须是有限数
+     */
+    pub fn validate(&self) -> error::Result<()> {
+        let values = [
+            ("x", self.x),
+            ("y", self.y),
+            ("width", self.width),
+            ("height", self.height),
+            ("angle", self.angle),
+            ("strokeWidth", self.stroke_width),
+            ("roughness", self.roughness),
+        ];
+        for (name, value) in values {
+            if !value.is_finite() {
+                return Err(Error::validation(
+                    &self.id,
+                    format!("{} is not a finite number", name),
+                ));
+            }
+        }
+        let points = self.points.iter().flatten();
+        if points
+            .into_iter()
+            .any(|point| !point.x.is_finite() || !point.y.is_finite())
+        {
+            return Err(Error::validation(
+                &self.id,
+                "points contain a non-finite coordinate",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn is_elbow_arrow(&self) -> bool {
+        self.element_type == ElementType::Arrow && self.elbowed == Some(true)
+    }
+
+    /**
+     * 元素未旋转时的包围盒（相对于元素原点），线条类元素以 points 为准
+     */
+    pub(crate) fn get_local_bounds(&self) -> (f64, f64, f64, f64) {
+        let is_linear = matches!(
+            self.element_type,
+            ElementType::Line | ElementType::Arrow | ElementType::Freedraw
+        );
+        match &self.points {
+            Some(points) if is_linear && !points.is_empty() => points.iter().fold(
+                (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+                |(min_x, min_y, max_x, max_y), point| {
+                    (
+                        min_x.min(point.x),
+                        min_y.min(point.y),
+                        max_x.max(point.x),
+                        max_y.max(point.y),
+                    )
+                },
+            ),
+            _ => (0.0, 0.0, self.width, self.height),
+        }
+    }
+
+    /**
+     * 旋转中心（相对于元素原点）
+     */
+    pub fn get_center(&self) -> (f64, f64) {
+        let (x1, y1, x2, y2) = self.get_local_bounds();
+        ((x1 + x2) / 2.0, (y1 + y2) / 2.0)
+    }
+
+    /**
+     * 获取文字行高（像素）
+     */
+    pub fn get_line_height_in_px(&self) -> f64 {
+        let font_size = self.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+        let line_height = self
+            .line_height
+            .unwrap_or_else(|| self.font_family.unwrap_or_default().default_line_height());
+        font_size * line_height
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -178,4 +485,163 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_bound_elements_deserialization() {
+        let json_str = r#"[
+            { "id": "text", "type": "text" },
+            { "id": "arrow", "type": "arrow" }
+        ]"#;
+
+        let bound_elements: Vec<BoundElement> = serde_json::from_str(json_str).unwrap();
+        assert_eq!(bound_elements[0].id, "text");
+        assert_eq!(bound_elements[0].type_field, ElementType::Text);
+        assert_eq!(bound_elements[1].type_field, ElementType::Arrow);
+    }
+
+    #[test]
+    fn test_text_element_deserialization() {
+        let json_str = r##"{
+            "id": "text",
+            "type": "text",
+            "x": 0, "y": 0, "width": 120, "height": 50, "angle": 0,
+            "strokeColor": "#1e1e1e", "backgroundColor": "transparent",
+            "fillStyle": "solid", "strokeWidth": 1, "strokeStyle": "solid",
+            "roughness": 1, "opacity": 100, "seed": 1, "version": 1,
+            "versionNonce": 1, "isDeleted": false, "updated": 1, "locked": false,
+            "roundness": null,
+            "text": "hello\nworld",
+            "originalText": "hello\nworld",
+            "fontSize": 20,
+            "fontFamily": 1,
+            "textAlign": "center",
+            "verticalAlign": "middle",
+            "lineHeight": 1.25,
+            "baseline": 43,
+            "containerId": "container"
+        }"##;
+
+        let element: Element = serde_json::from_str(json_str).unwrap();
+        assert_eq!(element.element_type, ElementType::Text);
+        assert_eq!(element.text.as_deref(), Some("hello\nworld"));
+        assert_eq!(element.font_family, Some(FontFamily::Virgil));
+        assert_eq!(element.text_align, Some(TextAlign::Center));
+        assert_eq!(element.vertical_align, Some(VerticalAlign::Middle));
+        assert_eq!(element.get_line_height_in_px(), 25.0);
+        assert_eq!(element.container_id.as_deref(), Some("container"));
+    }
+
+    #[test]
+    fn test_fractional_stroke_width_round_trip() {
+        let json_str = r##"{
+            "id": "rect",
+            "type": "rectangle",
+            "x": 0, "y": 0, "width": 10, "height": 10, "angle": 0,
+            "strokeColor": "#1e1e1e", "backgroundColor": "transparent",
+            "fillStyle": "solid", "strokeWidth": 0.1, "strokeStyle": "solid",
+            "roughness": 0.3, "opacity": 100, "seed": 1, "version": 1,
+            "versionNonce": 1, "isDeleted": false, "updated": 1, "locked": false,
+            "roundness": null
+        }"##;
+
+        let element: Element = serde_json::from_str(json_str).unwrap();
+        let value = serde_json::to_value(&element).unwrap();
+        assert_eq!(value["strokeWidth"], serde_json::json!(0.1));
+        assert_eq!(value["roughness"], serde_json::json!(0.3));
+    }
+
+    #[test]
+    fn test_missing_frame_id_and_bound_elements_round_trip() {
+        let json_str = r##"{
+            "id": "rect",
+            "type": "rectangle",
+            "x": 0, "y": 0, "width": 10, "height": 10, "angle": 0,
+            "strokeColor": "#1e1e1e", "backgroundColor": "transparent",
+            "fillStyle": "solid", "strokeWidth": 1, "strokeStyle": "solid",
+            "roughness": 1, "opacity": 100, "seed": 1, "version": 1,
+            "versionNonce": 1, "isDeleted": false, "updated": 1, "locked": false,
+            "roundness": null
+        }"##;
+
+        let element: Element = serde_json::from_str(json_str).unwrap();
+        assert_eq!(element.frame_id, None);
+        assert!(element.bound_elements.is_none());
+
+        let value = serde_json::to_value(&element).unwrap();
+        assert!(value.get("frameId").is_none());
+        assert!(value.get("boundElements").is_none());
+
+        let restored: Element = serde_json::from_value(value).unwrap();
+        assert_eq!(restored.frame_id, None);
+        assert!(restored.bound_elements.is_none());
+    }
+
+    #[test]
+    fn test_frame_element_type_deserialization() {
+        let element_types: Vec<ElementType> =
+            serde_json::from_str(r#"["frame", "magicframe", "freedraw", "image"]"#).unwrap();
+        assert_eq!(
+            element_types,
+            vec![
+                ElementType::Frame,
+                ElementType::MagicFrame,
+                ElementType::Freedraw,
+                ElementType::Image
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arrowhead_deserialization() {
+        let arrowheads: Vec<Arrowhead> = serde_json::from_str(
+            r#"["arrow", "circle_outline", "triangle_outline", "diamond", "crowfoot_one_or_many"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            arrowheads,
+            vec![
+                Arrowhead::Arrow,
+                Arrowhead::CircleOutline,
+                Arrowhead::TriangleOutline,
+                Arrowhead::Diamond,
+                Arrowhead::CrowfootOneOrMany
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elbow_arrow() {
+        let fixed_segments: Vec<FixedSegment> =
+            serde_json::from_str(r#"[{ "start": [50, 0], "end": [50, 40], "index": 2 }]"#).unwrap();
+        let element = Element {
+            element_type: ElementType::Arrow,
+            elbowed: Some(true),
+            fixed_segments: Some(fixed_segments),
+            ..Default::default()
+        };
+        assert!(element.is_elbow_arrow());
+        assert_eq!(
+            element.fixed_segments.unwrap()[0],
+            FixedSegment {
+                start: Point::new(50.0, 0.0),
+                end: Point::new(50.0, 40.0),
+                index: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_font_family_ids() {
+        let families: Vec<FontFamily> = serde_json::from_str("[4, 10, 42]").unwrap();
+        assert_eq!(
+            families,
+            vec![
+                FontFamily::LocalFont,
+                FontFamily::Assistant,
+                FontFamily::Unknown(42)
+            ]
+        );
+        assert_eq!(FontFamily::Unknown(42).font_name(), "Virgil");
+        assert_eq!(serde_json::to_string(&families).unwrap(), "[4,10,42]");
+    }
 }
@@ -0,0 +1,299 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes128Gcm, Nonce,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use miniz_oxide::{deflate::compress_to_vec_zlib, inflate::decompress_to_vec_zlib};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+const COMPRESSION_LEVEL: u8 = 6;
+/**
+ * concatBuffers 格式的版本号
+ */
+const CONCAT_BUFFERS_VERSION: u32 = 1;
+const IV_LENGTH: usize = 12;
+
+/**
+ * 与 excalidraw 的 EncodedData 一致，PNG / SVG 导出中嵌入的场景使用该格式
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EncodedData {
+    version: String,
+    encoding: String,
+    compressed: bool,
+    encoded: String,
+}
+
+/**
+ * 与 excalidraw 的 FileEncodingInfo 一致，描述 compressData 输出的格式
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FileEncodingInfo {
+    version: u32,
+    #[serde(default)]
+    compression: Option<String>,
+    encryption: String,
+}
+
+/**
+ * decompress_data 的结果，metadata 为 compress_data 时附带的 JSON
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecompressedData {
+    pub metadata: Value,
+    pub data: Vec<u8>,
+}
+
+/**
+ * excalidraw 的 bstring 编码：每个字节作为一个字符
+ */
+pub(crate) fn to_byte_string(bytes: &[u8]) -> String {
+    bytes.iter().copied().map(char::from).collect()
+}
+
+pub(crate) fn from_byte_string(byte_string: &str) -> Result<Vec<u8>> {
+    byte_string
+        .chars()
+        .map(|c| u8::try_from(c).map_err(|_| Error::decode("Invalid byte string")))
+        .collect()
+}
+
+/**
+ * 编码为 EncodedData 的 JSON，compress 时先用 zlib 压缩，与 excalidraw 的 encode 一致
+ */
+pub fn encode(text: &str, compress: bool) -> String {
+    let bytes = match compress {
+        true => compress_to_vec_zlib(text.as_bytes(), COMPRESSION_LEVEL),
+        false => text.as_bytes().to_vec(),
+    };
+    let data = EncodedData {
+        version: "1".to_string(),
+        encoding: "bstring".to_string(),
+        compressed: compress,
+        encoded: to_byte_string(&bytes),
+    };
+    // 只包含字符串和布尔值，不会序列化失败
+    serde_json::to_string(&data).unwrap_or_default()
+}
+
+/**
+ * 解码 EncodedData 的 JSON，返回原始文本，与 excalidraw 的 decode 一致
+ */
+pub fn decode(json: &str) -> Result<String> {
+    let data: EncodedData = serde_json::from_str(json)?;
+    if data.encoding != "bstring" {
+        return Err(Error::decode(format!(
+            "Unsupported encoding: {}",
+            data.encoding
+        )));
+    }
+    let bytes = from_byte_string(&data.encoded)?;
+    let bytes = match data.compressed {
+        true => inflate(&bytes)?,
+        false => bytes,
+    };
+    String::from_utf8(bytes).map_err(Error::decode)
+}
+
+/**
+ * 解码 PNG / SVG 中嵌入的场景，早期版本直接写入场景 JSON，没有 EncodedData 外层
+ */
+pub(crate) fn decode_scene_payload(text: &str) -> Result<String> {
+    let value: Value = serde_json::from_str(text)?;
+    if value.get("encoded").is_none() && value.get("type") == Some(&Value::from("excalidraw")) {
+        return Ok(text.to_string());
+    }
+    decode(text)
+}
+
+/**
+ * 按 excalidraw 的 concatBuffers 格式拼接：版本号后跟每段的长度与内容，整数均为大端 u32
+ */
+pub fn concat_buffers(buffers: &[&[u8]]) -> Vec<u8> {
+    let mut output = CONCAT_BUFFERS_VERSION.to_be_bytes().to_vec();
+    for buffer in buffers {
+        output.extend_from_slice(&(buffer.len() as u32).to_be_bytes());
+        output.extend_from_slice(buffer);
+    }
+    output
+}
+
+pub fn split_buffers(buffer: &[u8]) -> Result<Vec<&[u8]>> {
+    let read_u32 = |bytes: &[u8]| -> Result<u32> {
+        bytes
+            .get(..4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| Error::decode("Truncated buffer"))
+    };
+    let version = read_u32(buffer)?;
+    if version > CONCAT_BUFFERS_VERSION {
+        return Err(Error::decode(format!(
+            "Unsupported buffer version: {}",
+            version
+        )));
+    }
+    let mut rest = &buffer[4..];
+    let mut buffers = Vec::new();
+    while !rest.is_empty() {
+        let length = read_u32(rest)? as usize;
+        let end = length
+            .checked_add(4)
+            .ok_or_else(|| Error::decode("Invalid buffer length"))?;
+        let chunk = rest
+            .get(4..end)
+            .ok_or_else(|| Error::decode("Truncated buffer"))?;
+        buffers.push(chunk);
+        rest = &rest[end..];
+    }
+    Ok(buffers)
+}
+
+/**
+ * 与 excalidraw 的 compressData 一致：metadata 与数据压缩后用 AES-GCM 加密，用于分享链接和协作中的文件
+ */
+pub fn compress_data(
+    data: &[u8],
+    encryption_key: &str,
+    metadata: Option<&Value>,
+) -> Result<Vec<u8>> {
+    let file_info = FileEncodingInfo {
+        version: 2,
+        compression: Some("pako@1".to_string()),
+        encryption: "AES-GCM".to_string(),
+    };
+    let file_info = serde_json::to_vec(&file_info)?;
+    let metadata = serde_json::to_vec(&metadata.unwrap_or(&Value::Null))?;
+    let contents = compress_to_vec_zlib(&concat_buffers(&[&metadata, data]), COMPRESSION_LEVEL);
+    // IV 不能重复，必须来自系统的安全随机数
+    let mut iv = [0; IV_LENGTH];
+    getrandom::getrandom(&mut iv).map_err(Error::encode)?;
+    let encrypted = get_cipher(encryption_key)?
+        .encrypt(Nonce::from_slice(&iv), contents.as_slice())
+        .map_err(|_| Error::encode("Failed to encrypt data"))?;
+    Ok(concat_buffers(&[&file_info, &iv, &encrypted]))
+}
+
+/**
+ * 与 excalidraw 的 decompressData 一致
+ */
+pub fn decompress_data(buffer: &[u8], decryption_key: &str) -> Result<DecompressedData> {
+    let (file_info, iv, encrypted) = match split_buffers(buffer)?[..] {
+        [file_info, iv, encrypted] => (file_info, iv, encrypted),
+        _ => return Err(Error::decode("Invalid compressed data")),
+    };
+    let file_info: FileEncodingInfo = serde_json::from_slice(file_info)?;
+    let decrypted = decrypt(iv, encrypted, decryption_key)?;
+    let contents = match file_info.compression {
+        Some(_) => inflate(&decrypted)?,
+        None => decrypted,
+    };
+    match split_buffers(&contents)?[..] {
+        [metadata, data] => Ok(DecompressedData {
+            metadata: serde_json::from_slice(metadata)?,
+            data: data.to_vec(),
+        }),
+        _ => Err(Error::decode("Invalid compressed data")),
+    }
+}
+
+/**
+ * 解密分享链接中的场景，旧版本的分享链接使用全 0 的 IV 直接加密 JSON。
+ * 只有数据不是 compressData 的格式时才按旧版本解密，密钥错误等问题直接返回
+ */
+pub(crate) fn decode_share_link_data(buffer: &[u8], decryption_key: &str) -> Result<String> {
+    let is_legacy = match split_buffers(buffer).as_deref() {
+        Ok([file_info, _, _]) => serde_json::from_slice::<FileEncodingInfo>(file_info).is_err(),
+        _ => true,
+    };
+    let data = match is_legacy {
+        true => decrypt(&[0; IV_LENGTH], buffer, decryption_key)?,
+        false => decompress_data(buffer, decryption_key)?.data,
+    };
+    String::from_utf8(data).map_err(Error::decode)
+}
+
+fn decrypt(iv: &[u8], data: &[u8], key: &str) -> Result<Vec<u8>> {
+    if iv.len() != IV_LENGTH {
+        return Err(Error::decode("Invalid IV"));
+    }
+    get_cipher(key)?
+        .decrypt(Nonce::from_slice(iv), data)
+        .map_err(|_| Error::decode("Failed to decrypt data, the key may be wrong"))
+}
+
+/**
+ * key 为 excalidraw 生成的 JWK 中的 k 字段（base64url 编码的 128 位密钥）
+ */
+fn get_cipher(key: &str) -> Result<Aes128Gcm> {
+    let key = URL_SAFE_NO_PAD
+        .decode(key.trim_end_matches('='))
+        .map_err(|_| Error::decode("Invalid encryption key"))?;
+    Aes128Gcm::new_from_slice(&key).map_err(|_| Error::decode("Invalid encryption key"))
+}
+
+fn inflate(bytes: &[u8]) -> Result<Vec<u8>> {
+    decompress_to_vec_zlib(bytes)
+        .map_err(|e| Error::decode(format!("Failed to decompress: {:?}", e.status)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "hROAzavLDqW-gYMnLysUog";
+    const SCENE: &str = r##"{"type":"excalidraw","version":2,"source":"https://excalidraw.com","elements":[],"appState":{"viewBackgroundColor":"#ffffff"},"files":{}}"##;
+
+    #[test]
+    fn test_encode_decode() {
+        let text = r#"{"type":"excalidraw","text":"中文"}"#;
+        for compress in [true, false] {
+            let encoded = encode(text, compress);
+            assert!(encoded.starts_with(r#"{"version":"1","encoding":"bstring""#));
+            assert_eq!(decode(&encoded).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_decompress_excalidraw_data() {
+        // 由浏览器中的 compressData 生成
+        let buffer = base64::engine::general_purpose::STANDARD
+            .decode("AAAAAQAAADt7InZlcnNpb24iOjIsImNvbXByZXNzaW9uIjoicGFrb0AxIiwiZW5jcnlwdGlvbiI6IkFFUy1HQ00ifQAAAAzm4VTsP0pLIKZfGqQAAACUBPIdGvYoxV+pjFvaAhiTKdZGb4bgTw7SqG4SvJJ6FIyiHGtJx4Or/HIzeuIuvQnpYfE+1OfL1TBBcxa+8uwmnsCemgxzwidPdLCkNiQCORYnm196wDTaS7uZM6bfkL7WX0jQIAT/Z9MbCZoK7ybRBXYSP3G377I048fErzp4sfnx5v9+upvrZgI0OY60vix8+t00OQ==")
+            .unwrap();
+        let decompressed = decompress_data(&buffer, KEY).unwrap();
+        assert_eq!(decompressed.metadata, Value::Null);
+        assert_eq!(decompressed.data, SCENE.as_bytes());
+        assert!(decompress_data(&buffer, "AAAAAAAAAAAAAAAAAAAAAA").is_err());
+
+        let legacy = base64::engine::general_purpose::STANDARD
+            .decode("kd9SzGLWW6RiMzH6lLGSjjhoIV/nb93kmopabPkxtTdumTQmFoyP4PNOrH9pbGriXCq6zwf1iVUsZDT0VMCkEyFUMS8lxDTlZ7RjlMb37lFdQgGLRshIZI4diHv3fugavz+DYUsVLKv0T9DYx3otH+bLYBgRMU7oflKZ41glHorlAJm0CgR+eCcEJXC8DydTu+uJnlU8zvtw")
+            .unwrap();
+        assert_eq!(decode_share_link_data(&legacy, KEY).unwrap(), SCENE);
+        assert_eq!(decode_share_link_data(&buffer, KEY).unwrap(), SCENE);
+        assert!(decode_share_link_data(&buffer, "AAAAAAAAAAAAAAAAAAAAAA").is_err());
+    }
+
+    #[test]
+    fn test_split_buffers_rejects_invalid_length() {
+        let buffer = [0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff, 0];
+        assert!(matches!(split_buffers(&buffer), Err(Error::Decode(_))));
+    }
+
+    #[test]
+    fn test_compress_data_round_trip() {
+        let metadata = serde_json::json!({ "id": "file-1", "mimeType": "image/png" });
+        let buffer = compress_data(b"data:image/png;base64,AAAA", KEY, Some(&metadata)).unwrap();
+        let decompressed = decompress_data(&buffer, KEY).unwrap();
+        assert_eq!(decompressed.metadata, metadata);
+        assert_eq!(decompressed.data, b"data:image/png;base64,AAAA");
+
+        // 每次加密使用不同的 IV
+        let other = compress_data(b"data:image/png;base64,AAAA", KEY, Some(&metadata)).unwrap();
+        assert_ne!(
+            split_buffers(&buffer).unwrap()[1],
+            split_buffers(&other).unwrap()[1]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /**
+     * 场景 JSON 解析或序列化失败
+     */
+    Parse(serde_json::Error),
+    /**
+     * 嵌入在 PNG / SVG 等文件中的场景数据无法解码
+     */
+    Decode(String),
+    /**
+     * 导出文件时编码失败
+     */
+    Encode(String),
+    /**
+     * 元素数据不合法，无法绘制
+     */
+    Validation { id: String, message: String },
+    /**
+     * 绘制元素时出错
+     */
+    Render { id: String, message: String },
+}
+
+impl Error {
+    pub(crate) fn decode(message: impl fmt::Display) -> Self {
+        Error::Decode(message.to_string())
+    }
+
+    pub(crate) fn encode(message: impl fmt::Display) -> Self {
+        Error::Encode(message.to_string())
+    }
+
+    pub(crate) fn validation(id: &str, message: impl Into<String>) -> Self {
+        Error::Validation {
+            id: id.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn render(id: &str, message: impl fmt::Display) -> Self {
+        Error::Render {
+            id: id.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "Failed to parse scene: {}", e),
+            Error::Decode(message) => write!(f, "Failed to decode scene: {}", message),
+            Error::Encode(message) => write!(f, "Failed to encode: {}", message),
+            Error::Validation { id, message } => {
+                write!(f, "Invalid element {}: {}", id, message)
+            }
+            Error::Render { id, message } => {
+                write!(f, "Failed to render element {}: {}", id, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e)
+    }
+}
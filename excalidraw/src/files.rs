@@ -0,0 +1,207 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageFormat;
+use resvg::{
+    tiny_skia,
+    usvg::{self, fontdb, TreeParsing, TreePostProc},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    encode,
+    error::{Error, Result},
+};
+
+pub type BinaryFiles = HashMap<String, BinaryFileData>;
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryFileData {
+    pub id: String,
+    pub mime_type: String,
+    #[serde(rename = "dataURL")]
+    pub data_url: String,
+    pub created: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_retrieved: Option<i64>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/**
+ * 解码后的位图，像素为预乘 alpha 的 RGBA
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl BinaryFileData {
+    /**
+     * 解析 dataURL，返回 mime type 与二进制内容
+     */
+    pub fn decode_data_url(&self) -> Result<(String, Vec<u8>)> {
+        let data = self
+            .data_url
+            .strip_prefix("data:")
+            .ok_or_else(|| Error::decode("Invalid data URL"))?;
+        let (meta, content) = data
+            .split_once(',')
+            .ok_or_else(|| Error::decode("Invalid data URL"))?;
+        let meta = meta
+            .strip_suffix(";base64")
+            .ok_or_else(|| Error::decode("Unsupported data URL encoding"))?;
+        let mime_type = if meta.is_empty() {
+            self.mime_type.clone()
+        } else {
+            meta.to_string()
+        };
+        let bytes = STANDARD.decode(content.trim()).map_err(Error::decode)?;
+        Ok((mime_type, bytes))
+    }
+
+    /**
+     * 解密协作或分享链接中上传的文件，metadata 中为文件信息，内容为 dataURL
+     */
+    pub fn from_encrypted(buffer: &[u8], decryption_key: &str) -> Result<Self> {
+        let decompressed = encode::decompress_data(buffer, decryption_key)?;
+        let data_url = String::from_utf8(decompressed.data).map_err(Error::decode)?;
+        let mut metadata = decompressed.metadata;
+        if let Value::Object(map) = &mut metadata {
+            map.insert("dataURL".to_string(), Value::String(data_url));
+        }
+        Ok(serde_json::from_value(metadata)?)
+    }
+
+    pub fn decode(&self) -> Result<DecodedImage> {
+        let (mime_type, bytes) = self.decode_data_url()?;
+        match mime_type.as_str() {
+            "image/svg+xml" => decode_svg(&bytes),
+            "image/png" => decode_bitmap(&bytes, ImageFormat::Png),
+            "image/jpeg" | "image/jpg" => decode_bitmap(&bytes, ImageFormat::Jpeg),
+            "image/webp" => decode_bitmap(&bytes, ImageFormat::WebP),
+            _ => Err(Error::decode(format!(
+                "Unsupported image type: {}",
+                mime_type
+            ))),
+        }
+    }
+}
+
+fn decode_bitmap(bytes: &[u8], format: ImageFormat) -> Result<DecodedImage> {
+    let image = image::load_from_memory_with_format(bytes, format)
+        .map_err(Error::decode)?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    let mut pixels = image.into_raw();
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u16;
+        for channel in pixel.iter_mut().take(3) {
+            *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+        }
+    }
+    Ok(DecodedImage {
+        width: width as usize,
+        height: height as usize,
+        pixels,
+    })
+}
+
+fn decode_svg(bytes: &[u8]) -> Result<DecodedImage> {
+    let mut tree =
+        usvg::Tree::from_data(bytes, &usvg::Options::default()).map_err(Error::decode)?;
+    tree.postprocess(usvg::PostProcessingSteps::default(), get_fontdb());
+    let size = tree.size.to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| Error::decode("Invalid svg size"))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    Ok(DecodedImage {
+        width: pixmap.width() as usize,
+        height: pixmap.height() as usize,
+        pixels: pixmap.take(),
+    })
+}
+
+/**
+ * 加载系统字体开销较大，只初始化一次
+ */
+pub(crate) fn get_fontdb() -> &'static fontdb::Database {
+    static FONTDB: OnceLock<fontdb::Database> = OnceLock::new();
+    FONTDB.get_or_init(|| {
+        let mut fontdb = fontdb::Database::new();
+        fontdb.load_system_fonts();
+        fontdb
+    })
+}
+
+/**
+ * 按名称查找系统字体，只接受家族名完全一致的字体。
+ * 系统字体可能是字体集合（ttc），PDF 和 SVG 都只能嵌入单个字体，此时放弃嵌入
+ */
+pub(crate) fn load_system_font(name: &str) -> Option<Vec<u8>> {
+    load_font(fontdb::Family::Name(name))
+}
+
+/**
+ * 与字体同类的通用系统字体。只用于按字形子集嵌入的 PDF，
+ * SVG 会完整内嵌字体文件，不能用它冒充 Virgil 等字体
+ */
+pub(crate) fn load_fallback_font(name: &str) -> Option<Vec<u8>> {
+    load_font(match name {
+        "Cascadia" | "Comic Shanns" => fontdb::Family::Monospace,
+        _ => fontdb::Family::SansSerif,
+    })
+}
+
+fn load_font(family: fontdb::Family) -> Option<Vec<u8>> {
+    let fontdb = get_fontdb();
+    let id = fontdb.query(&fontdb::Query {
+        families: &[family],
+        ..Default::default()
+    })?;
+    fontdb
+        .with_face_data(id, |data, index| (index == 0).then(|| data.to_vec()))
+        .flatten()
+        .filter(|bytes| !bytes.starts_with(b"ttcf"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_system_font_exact_match() {
+        assert!(load_system_font("Excalidraw Missing Font").is_none());
+    }
+
+    #[test]
+    fn test_decode_data_url() {
+        let file = BinaryFileData {
+            id: "file".to_string(),
+            mime_type: "image/svg+xml".to_string(),
+            data_url: format!("data:image/svg+xml;base64,{}", STANDARD.encode("<svg/>")),
+            created: 0,
+            last_retrieved: None,
+            extra: Map::new(),
+        };
+        let (mime_type, bytes) = file.decode_data_url().unwrap();
+        assert_eq!(mime_type, "image/svg+xml");
+        assert_eq!(bytes, b"<svg/>");
+    }
+
+    #[test]
+    fn test_decode_svg() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2"><rect width="4" height="2" fill="red"/></svg>"#;
+        let file = BinaryFileData {
+            data_url: format!("data:image/svg+xml;base64,{}", STANDARD.encode(svg)),
+            ..Default::default()
+        };
+        let image = file.decode().unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+        assert_eq!(&image.pixels[0..4], &[255, 0, 0, 255]);
+    }
+}
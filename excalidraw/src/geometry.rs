@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use piet::kurbo::{Rect as KurboRect, Shape};
+
+use crate::{
+    draw::{
+        get_arrowhead_bounds, get_bound_text_position, get_container, get_free_draw_path,
+        get_name_height, get_transform, DrawConfig, FRAME_STROKE_WIDTH,
+    },
+    element::{Element, ElementType, StrokeStyle, DEFAULT_FONT_SIZE},
+    Rect,
+};
+
+/**
+ * 估算文字宽度时每个字符占字号的比例，中日韩等全角字符占满一个字号
+ */
+const TEXT_CHAR_WIDTH_RATIO: f64 = 0.6;
+const WIDE_CHAR_WIDTH_RATIO: f64 = 1.0;
+/**
+ * 与 roughr 的 max_randomness_offset 默认值一致
+ */
+const MAX_RANDOMNESS_OFFSET: f64 = 2.0;
+
+/**
+ * 按字符数估算多行文字的宽高
+ */
+pub fn estimate_text_size(text: &str, font_size: f64, line_height_in_px: f64) -> (f64, f64) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let max_width = lines
+        .iter()
+        .map(|line| line.chars().map(get_char_width_ratio).sum::<f64>())
+        .fold(0.0, f64::max);
+    (
+        max_width * font_size,
+        lines.len() as f64 * line_height_in_px,
+    )
+}
+
+/**
+ * 中日韩文字、全角符号和 emoji 按全角计算，其余字符按平均宽度计算
+ */
+fn get_char_width_ratio(c: char) -> f64 {
+    match c {
+        '\u{1100}'..='\u{115F}'
+        | '\u{2E80}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}'
+        | '\u{1F300}'..='\u{1F64F}'
+        | '\u{1F900}'..='\u{1F9FF}'
+        | '\u{20000}'..='\u{3FFFD}' => WIDE_CHAR_WIDTH_RATIO,
+        _ => TEXT_CHAR_WIDTH_RATIO,
+    }
+}
+
+/**
+ * 元素绘制后实际占用的外接矩形（绝对坐标），包含线宽、箭头、手绘抖动、文字与 frame 名称
+ */
+pub fn get_element_bounds(element: &Element) -> Rect {
+    let rect = get_element_bounds_rect(element, None);
+    Rect {
+        x: rect.x0,
+        y: rect.y0,
+        width: rect.width(),
+        height: rect.height(),
+    }
+}
+
+/**
+ * 所有未删除元素的外接矩形，没有元素时返回空矩形
+ */
+pub fn get_scene_bounds(elements: &[Element]) -> Rect {
+    let element_map: HashMap<&str, &Element> = elements
+        .iter()
+        .map(|element| (element.id.as_str(), element))
+        .collect();
+    let rect = elements
+        .iter()
+        // 无法绘制的元素不参与计算，避免 NaN 污染整个画布
+        .filter(|element| !element.is_deleted && element.validate().is_ok())
+        .map(|element| get_element_bounds_rect(element, get_container(element, &element_map)))
+        .reduce(|acc, rect| acc.union(rect));
+    match rect {
+        Some(rect) => Rect {
+            x: rect.x0,
+            y: rect.y0,
+            width: rect.width(),
+            height: rect.height(),
+        },
+        None => Rect::default(),
+    }
+}
+
+/**
+ * container 为绑定文字所在的容器，文字的位置与绘制时一样由容器决定
+ */
+fn get_element_bounds_rect(element: &Element, container: Option<&Element>) -> KurboRect {
+    let (x1, y1, x2, y2) = element.get_local_bounds();
+    let mut rect = KurboRect::new(x1, y1, x2, y2);
+    let position = match container {
+        Some(container) if element.element_type == ElementType::Text => {
+            get_bound_text_position(element, container)
+        }
+        _ => (element.x, element.y),
+    };
+
+    match element.element_type {
+        ElementType::Arrow => {
+            let arrowheads = [
+                (true, &element.start_arrowhead),
+                (false, &element.end_arrowhead),
+            ];
+            for (is_start, arrowhead) in arrowheads {
+                if let Some(bounds) = arrowhead
+                    .as_ref()
+                    .and_then(|arrowhead| get_arrowhead_bounds(element, is_start, arrowhead))
+                {
+                    rect = rect.union(bounds);
+                }
+            }
+        }
+        ElementType::Freedraw => {
+            // 笔迹轮廓已经包含了线宽
+            rect = rect.union(get_free_draw_path(element).bounding_box());
+            return transform_local_rect(element, position, rect);
+        }
+        ElementType::Text => {
+            if let Some(text) = &element.text {
+                // 优先使用保存的宽高（由 excalidraw 按实际字体测量），缺失时按字符数估算
+                if element.width <= 0.0 || element.height <= 0.0 {
+                    let font_size = element.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+                    let (width, height) =
+                        estimate_text_size(text, font_size, element.get_line_height_in_px());
+                    if element.width <= 0.0 {
+                        rect.x1 = rect.x0 + width;
+                    }
+                    if element.height <= 0.0 {
+                        rect.y1 = rect.y0 + height;
+                    }
+                }
+            }
+            return transform_local_rect(element, position, rect);
+        }
+        ElementType::Frame | ElementType::MagicFrame => {
+            rect.y0 -= get_name_height();
+            return transform_local_rect(
+                element,
+                position,
+                rect.inflate(FRAME_STROKE_WIDTH / 2.0, 0.0),
+            );
+        }
+        _ => {}
+    }
+
+    transform_local_rect(
+        element,
+        position,
+        rect.inflate(get_margin(element), get_margin(element)),
+    )
+}
+
+/**
+ * 线宽的一半加上手绘抖动与弯曲的幅度
+ */
+fn get_margin(element: &Element) -> f64 {
+    let stroke_width = match element.stroke_style {
+        StrokeStyle::Solid => element.stroke_width,
+        _ => element.stroke_width + 0.5,
+    };
+    let roughness = element.roughness;
+    // roughr 的线条弯曲幅度约为长度的 1/100
+    let bowing = element.width.abs().max(element.height.abs()) / 100.0;
+    stroke_width / 2.0 + roughness * (MAX_RANDOMNESS_OFFSET + bowing)
+}
+
+/**
+ * 将相对于元素原点的矩形绕元素中心旋转后平移到绝对坐标，与绘制时的变换一致
+ */
+fn transform_local_rect(element: &Element, position: (f64, f64), rect: KurboRect) -> KurboRect {
+    let (x, y) = position;
+    get_transform(
+        x,
+        y,
+        element.get_center(),
+        element.angle,
+        &DrawConfig::default(),
+    )
+    .transform_rect_bbox(rect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{element::Arrowhead, point::Point};
+
+    #[test]
+    fn test_arrow_bounds_include_negative_points_and_arrowheads() {
+        let arrow = Element {
+            element_type: ElementType::Arrow,
+            x: 100.0,
+            y: 100.0,
+            points: Some(vec![Point::new(0.0, 0.0), Point::new(-100.0, 0.0)]),
+            end_arrowhead: Some(Arrowhead::Triangle),
+            ..Default::default()
+        };
+        let bounds = get_element_bounds(&arrow);
+        assert_eq!(bounds.x, 0.0);
+        assert_eq!(bounds.x + bounds.width, 100.0);
+        // 水平线段本身没有高度，箭头在终点处向两侧展开
+        assert!(bounds.y < 100.0);
+        assert!(bounds.y + bounds.height > 100.0);
+    }
+
+    #[test]
+    fn test_text_bounds() {
+        assert_eq!(estimate_text_size("ab\nabc", 10.0, 12.0), (18.0, 24.0));
+        assert_eq!(estimate_text_size("中文", 10.0, 12.0), (20.0, 12.0));
+        assert_eq!(estimate_text_size("a中", 10.0, 12.0), (16.0, 12.0));
+
+        // 保存的宽高即使小于估算值也不会被覆盖
+        let text = Element {
+            element_type: ElementType::Text,
+            width: 30.0,
+            height: 10.0,
+            text: Some("hello\nworld".to_string()),
+            font_size: Some(20.0),
+            ..Default::default()
+        };
+        let bounds = get_element_bounds(&text);
+        assert_eq!((bounds.width, bounds.height), (30.0, 10.0));
+
+        let bounds = get_element_bounds(&Element {
+            width: 0.0,
+            height: 0.0,
+            ..text
+        });
+        assert_eq!(bounds.width, 60.0);
+        assert!(bounds.height > 10.0);
+    }
+
+    #[test]
+    fn test_scene_bounds_skip_deleted_and_include_stroke() {
+        let elements = vec![
+            Element {
+                width: 100.0,
+                height: 100.0,
+                stroke_width: 4.0,
+                ..Default::default()
+            },
+            Element {
+                x: 1000.0,
+                width: 100.0,
+                height: 100.0,
+                is_deleted: true,
+                ..Default::default()
+            },
+        ];
+        let bounds = get_scene_bounds(&elements);
+        assert_eq!(bounds.x, -2.0);
+        assert_eq!(bounds.width, 104.0);
+    }
+
+    #[test]
+    fn test_bound_text_bounds_follow_container() {
+        let elements = vec![
+            Element {
+                id: "container".to_string(),
+                width: 200.0,
+                height: 100.0,
+                ..Default::default()
+            },
+            // 文字自身的 x / y 已经过期，绘制时以容器为准
+            Element {
+                element_type: ElementType::Text,
+                x: 1000.0,
+                y: 1000.0,
+                width: 50.0,
+                height: 25.0,
+                text: Some("text".to_string()),
+                container_id: Some("container".to_string()),
+                ..Default::default()
+            },
+        ];
+        let bounds = get_scene_bounds(&elements);
+        assert!(bounds.x + bounds.width < 300.0);
+        assert!(bounds.y + bounds.height < 200.0);
+    }
+}
@@ -1,11 +1,32 @@
 mod draw;
 mod element;
+pub mod encode;
+mod error;
+mod files;
+pub mod geometry;
+mod library;
+pub mod model;
+pub mod obsidian;
+mod pdf;
+mod png;
 mod point;
+mod random;
+mod restore;
+mod svg;
 use draw::DrawConfig;
+pub use draw::Theme;
 use element::Element;
+pub use error::{Error, Result};
+pub use files::{BinaryFileData, BinaryFiles};
+pub use library::{Library, LibraryItem, LibraryItemStatus};
+pub use pdf::PdfOptions;
+pub use restore::{Repair, RepairKind};
+pub use svg::SvgOptions;
 
+use log::warn;
 use piet::RenderContext;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,82 +36,269 @@ pub struct Excalidraw {
     pub version: i64,
     pub source: String,
     pub elements: Vec<Element>,
+    #[serde(default)]
     pub app_state: AppState,
+    #[serde(default)]
+    pub files: BinaryFiles,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Rect {
-    pub x: f32,
-    pub y: f32,
-    pub width: f32,
-    pub height: f32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct AppState {
+    /**
+     * 画布背景色，缺省时与 excalidraw 一致为白色，`transparent` 表示透明
+     */
     pub view_background_color: String,
+    /**
+     * 其余 appState 字段原样保留
+     */
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            view_background_color: "#ffffff".to_string(),
+            extra: Map::new(),
+        }
+    }
 }
 
 impl Excalidraw {
-    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str(json)
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
     }
 
-    pub fn to_json(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string(self)
+    /**
+     * 宽松加载：先按 excalidraw 的 restore 规则补齐缺失字段、迁移旧字段，再解析，同时返回修复记录
+     */
+    pub fn restore(json: &str) -> Result<(Self, Vec<Repair>)> {
+        let mut value: Value = serde_json::from_str(json)?;
+        let repairs = restore::restore(&mut value);
+        Ok((serde_json::from_value(value)?, repairs))
     }
 
-    pub fn draw(&self, ctx: &mut impl RenderContext, padding: f32) {
+    /**
+     * 读取 excalidraw 导出时嵌入在 PNG 中的场景
+     */
+    pub fn from_png(bytes: &[u8]) -> Result<Self> {
+        Self::from_json(&png::decode_png(bytes)?)
+    }
+
+    /**
+     * 将渲染好的 RGBA 像素（非预乘 alpha）编码为 PNG，embed_scene 时把场景写入 PNG，可以拖回 excalidraw 继续编辑
+     */
+    pub fn to_png(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        embed_scene: bool,
+    ) -> Result<Vec<u8>> {
+        let scene = match embed_scene {
+            true => Some(self.to_json()?),
+            false => None,
+        };
+        png::encode_png(pixels, width, height, scene.as_deref())
+    }
+
+    /**
+     * 读取 excalidraw 导出 SVG 时嵌入的场景
+     */
+    pub fn from_svg(svg: &str) -> Result<Self> {
+        Self::from_json(&svg::decode_svg_metadata(svg)?)
+    }
+
+    /**
+     * 读取分享链接对应的加密数据，key 为链接 `#json=id,key` 中的 key
+     */
+    pub fn from_encrypted(buffer: &[u8], decryption_key: &str) -> Result<Self> {
+        Self::from_json(&encode::decode_share_link_data(buffer, decryption_key)?)
+    }
+
+    /**
+     * 读取 Obsidian Excalidraw 插件保存的 `.excalidraw.md`
+     */
+    pub fn from_obsidian(markdown: &str) -> Result<Self> {
+        Self::from_json(&obsidian::decode(markdown)?)
+    }
+
+    /**
+     * 保存为 Obsidian Excalidraw 插件的 `.excalidraw.md`，compress 时场景使用 LZ-string 压缩
+     */
+    pub fn to_obsidian(&self, compress: bool) -> Result<String> {
+        obsidian::encode(&self.to_json()?, compress)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /**
+     * 检查所有未删除的元素，返回第一个不合法的元素
+     */
+    pub fn validate(&self) -> Result<()> {
+        self.elements
+            .iter()
+            .filter(|element| !element.is_deleted)
+            .try_for_each(Element::validate)
+    }
+
+    /**
+     * 绘制场景，无法绘制的元素会被跳过，返回这些元素的错误
+     */
+    pub fn draw(&self, ctx: &mut impl RenderContext, padding: f64) -> Vec<Error> {
+        self.draw_with_theme(ctx, padding, Theme::Light)
+    }
+
+    /**
+     * 按主题绘制，深色模式下元素颜色与 excalidraw 导出时的深色滤镜一致，图片保持原样
+     */
+    pub fn draw_with_theme(
+        &self,
+        ctx: &mut impl RenderContext,
+        padding: f64,
+        theme: Theme,
+    ) -> Vec<Error> {
         let rect = self.get_canvas_size();
         draw::draw(
             ctx,
             &self.elements,
-            &DrawConfig {
-                offset_x: -rect.x + padding,
-                offset_y: -rect.y + padding,
-            },
-        );
+            &self.files,
+            &self.get_draw_config(&rect, padding, theme),
+        )
     }
+
     /**
-     * 获取画布大小（所有 elements 的外接矩形）
+     * 导出时使用的背景色，viewBackgroundColor 为透明或无法解析时返回 None
      */
-    pub fn get_canvas_size(&self) -> Rect {
-        if &self.elements.len() == &(0 as usize) {
-            return Rect {
-                x: 0.0,
-                y: 0.0,
-                width: 0.0,
-                height: 0.0,
-            };
+    pub fn get_background_color(&self, theme: Theme) -> Option<piet::Color> {
+        draw::color_from_hex(&self.app_state.view_background_color, 100)
+            .filter(|color| color.as_rgba8().3 > 0)
+            .map(|color| draw::apply_theme(color, theme))
+    }
+
+    fn get_draw_config(&self, rect: &Rect, padding: f64, theme: Theme) -> DrawConfig {
+        DrawConfig {
+            offset_x: -rect.x + padding,
+            offset_y: -rect.y + padding,
+            background_color: self.app_state.view_background_color.clone(),
+            theme,
+        }
+    }
+    /**
+     * 导出为独立的 SVG，与位图使用相同的 roughr 路径
+     */
+    pub fn to_svg(&self, options: &SvgOptions) -> Result<String> {
+        let rect = self.get_canvas_size();
+        let width = rect.width + options.padding * 2.0;
+        let height = rect.height + options.padding * 2.0;
+        let mut ctx = svg::SvgRenderContext::new(options);
+        if let Some(color) = self
+            .get_background_color(options.theme)
+            .filter(|_| options.background)
+        {
+            ctx.clear(piet::kurbo::Rect::new(0.0, 0.0, width, height), color);
+        }
+        self.draw_with_theme(&mut ctx, options.padding, options.theme)
+            .iter()
+            .for_each(|e| warn!("Skipped element: {}", e));
+        let scene = match options.embed_scene {
+            true => Some(self.to_json()?),
+            false => None,
         };
-        let first_element = &self.elements[0];
-        let mut min_x = first_element.x;
-        let mut min_y = first_element.y;
-        let mut max_x = min_x;
-        let mut max_y = min_y;
-        for element in &self.elements {
-            if element.x < min_x {
-                min_x = element.x;
-            }
-            if element.y < min_y {
-                min_y = element.y;
-            }
-            if element.x + element.width > max_x {
-                max_x = element.x + element.width;
+        Ok(ctx.into_svg(width, height, scene.as_deref()))
+    }
+
+    /**
+     * 导出为 PDF，frames_as_pages 时每个 frame 单独一页，不属于任何 frame 的元素放在最后一页，
+     * 页面大小为内容外接矩形加上 padding
+     */
+    pub fn to_pdf(&self, options: &PdfOptions) -> Result<Vec<u8>> {
+        let frames: Vec<&Element> = self
+            .elements
+            .iter()
+            .filter(|element| !element.is_deleted && element.is_frame())
+            .collect();
+        let pages: Vec<(Rect, Vec<Element>)> = match options.frames_as_pages && !frames.is_empty() {
+            true => {
+                let mut pages: Vec<(Rect, Vec<Element>)> = frames
+                    .iter()
+                    .map(|frame| {
+                        let elements = self
+                            .elements
+                            .iter()
+                            .filter(|element| {
+                                element.id == frame.id
+                                    || element.frame_id.as_deref() == Some(frame.id.as_str())
+                            })
+                            .cloned()
+                            .collect();
+                        (geometry::get_element_bounds(frame), elements)
+                    })
+                    .collect();
+                // 不属于任何 frame 的元素放在最后单独一页
+                let unframed: Vec<Element> = self
+                    .elements
+                    .iter()
+                    .filter(|element| {
+                        !element.is_deleted
+                            && !element.is_frame()
+                            && !element.frame_id.as_deref().is_some_and(|frame_id| {
+                                frames.iter().any(|frame| frame.id == frame_id)
+                            })
+                    })
+                    .cloned()
+                    .collect();
+                if !unframed.is_empty() {
+                    pages.push((geometry::get_scene_bounds(&unframed), unframed));
+                }
+                pages
             }
-            if element.y + element.height > max_y {
-                max_y = element.y + element.height;
+            false => vec![(self.get_canvas_size(), self.elements.clone())],
+        };
+
+        let mut document = pdf::PdfDocument::new(&options.fonts);
+        for (rect, elements) in pages {
+            let width = rect.width + options.padding * 2.0;
+            let height = rect.height + options.padding * 2.0;
+            let mut page = document.new_page(width, height);
+            if let Some(color) = self
+                .get_background_color(options.theme)
+                .filter(|_| options.background)
+            {
+                page.clear(piet::kurbo::Rect::new(0.0, 0.0, width, height), color);
             }
+            draw::draw(
+                &mut page,
+                &elements,
+                &self.files,
+                &self.get_draw_config(&rect, options.padding, options.theme),
+            )
+            .iter()
+            .for_each(|e| warn!("Skipped element: {}", e));
+            document.add_page(page);
         }
+        Ok(document.finish())
+    }
 
-        Rect {
-            x: min_x,
-            y: min_y,
-            width: max_x - min_x,
-            height: max_y - min_y,
-        }
+    /**
+     * 获取画布大小（所有 elements 的外接矩形）
+     */
+    pub fn get_canvas_size(&self) -> Rect {
+        geometry::get_scene_bounds(&self.elements)
     }
 }
 
@@ -104,4 +312,169 @@ mod tests {
         let excalidraw = Excalidraw::from_json(&file);
         assert!(excalidraw.is_ok())
     }
+
+    #[test]
+    fn test_canvas_size_with_rotated_element() {
+        let excalidraw = Excalidraw {
+            elements: vec![Element {
+                width: 100.0,
+                height: 50.0,
+                angle: std::f64::consts::FRAC_PI_2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let rect = excalidraw.get_canvas_size();
+        assert!((rect.x - 25.0).abs() < 1e-4);
+        assert!((rect.y + 25.0).abs() < 1e-4);
+        assert!((rect.width - 50.0).abs() < 1e-4);
+        assert!((rect.height - 100.0).abs() < 1e-4);
+    }
+
+    /**
+     * 数字统一按 f64 比较，JSON 中 `1` 与 `1.0` 语义相同
+     */
+    fn normalize(value: Value) -> Value {
+        match value {
+            Value::Number(n) => serde_json::json!(n.as_f64()),
+            Value::Array(items) => Value::Array(items.into_iter().map(normalize).collect()),
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, normalize(value)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    #[test]
+    fn test_round_trip_preserves_unknown_fields() {
+        let json = r##"{
+            "type": "excalidraw",
+            "version": 2,
+            "source": "https://excalidraw.com",
+            "elements": [
+                {
+                    "id": "rect",
+                    "type": "rectangle",
+                    "x": 602.8046875123456,
+                    "y": -346.43359375,
+                    "width": 440.14453125,
+                    "height": 335.390625,
+                    "angle": 0,
+                    "strokeColor": "#2f9e44",
+                    "backgroundColor": "#ffc9c9",
+                    "fillStyle": "hachure",
+                    "strokeWidth": 1,
+                    "strokeStyle": "solid",
+                    "roughness": 1,
+                    "opacity": 100,
+                    "groupIds": ["group"],
+                    "frameId": "frame",
+                    "index": "a0",
+                    "roundness": { "type": 3 },
+                    "seed": 887266057,
+                    "version": 66,
+                    "versionNonce": 1219123719,
+                    "isDeleted": false,
+                    "boundElements": [{ "id": "text", "type": "text" }],
+                    "updated": 1692966278657,
+                    "link": "https://example.com",
+                    "locked": false,
+                    "customData": { "owner": { "name": "tool" } }
+                }
+            ],
+            "appState": {
+                "viewBackgroundColor": "#ffffff",
+                "gridSize": 20,
+                "currentItemFontFamily": 1
+            },
+            "files": {
+                "file": {
+                    "id": "file",
+                    "mimeType": "image/png",
+                    "dataURL": "data:image/png;base64,",
+                    "created": 1692966278657,
+                    "version": 2
+                }
+            },
+            "libraryItems": []
+        }"##;
+        let excalidraw = Excalidraw::from_json(json).unwrap();
+        let output = excalidraw.to_json().unwrap();
+        assert_eq!(
+            normalize(serde_json::from_str(&output).unwrap()),
+            normalize(serde_json::from_str(json).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_restore_partial_scene() {
+        let json = r#"{
+            "elements": [
+                { "type": "arrow", "id": "arrow", "x": 10, "y": 10, "width": 100, "height": 0 },
+                { "type": "text", "id": "text", "text": "hello", "fontFamily": "Virgil" }
+            ]
+        }"#;
+        assert!(Excalidraw::from_json(json).is_err());
+        let (excalidraw, repairs) = Excalidraw::restore(json).unwrap();
+        assert_eq!(excalidraw.elements.len(), 2);
+        assert!(!repairs.is_empty());
+        let arrow = &excalidraw.elements[0];
+        assert_eq!(arrow.points.as_ref().map(Vec::len), Some(2));
+        assert_eq!(arrow.end_arrowhead, Some(element::Arrowhead::Arrow));
+        assert_eq!(
+            excalidraw.elements[1].font_family,
+            Some(element::FontFamily::Virgil)
+        );
+    }
+
+    #[test]
+    fn test_draw_reports_invalid_elements() {
+        let mut ctx = piet::NullRenderContext::new();
+        let mut excalidraw = Excalidraw {
+            elements: vec![Element {
+                id: "arrow".to_string(),
+                element_type: element::ElementType::Arrow,
+                points: Some(vec![point::Point::default()]),
+                end_arrowhead: Some(element::Arrowhead::Arrow),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(excalidraw.draw(&mut ctx, 0.0).is_empty());
+
+        // 出错的元素被跳过，不影响其余元素的绘制
+        excalidraw.elements[0].x = f64::NAN;
+        excalidraw.elements.push(Element {
+            id: "rect".to_string(),
+            width: 10.0,
+            height: 10.0,
+            ..Default::default()
+        });
+        let errors = excalidraw.draw(&mut ctx, 0.0);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], Error::Validation { id, .. } if id == "arrow"));
+        assert_eq!(excalidraw.get_canvas_size().width, 10.0);
+    }
+
+    #[test]
+    fn test_background_color() {
+        let json = r#"{"type":"excalidraw","version":2,"source":"","elements":[]}"#;
+        let mut excalidraw = Excalidraw::from_json(json).unwrap();
+        assert_eq!(excalidraw.app_state.view_background_color, "#ffffff");
+        assert_eq!(
+            excalidraw.get_background_color(Theme::Light),
+            Some(piet::Color::WHITE)
+        );
+
+        // 深色模式的白色背景与 excalidraw 一致为 #121212
+        let dark = excalidraw.get_background_color(Theme::Dark).unwrap();
+        assert_eq!(dark.as_rgba8(), (0x12, 0x12, 0x12, 0xff));
+
+        excalidraw.app_state.view_background_color = "transparent".to_string();
+        assert_eq!(excalidraw.get_background_color(Theme::Light), None);
+        let svg = excalidraw.to_svg(&SvgOptions::default()).unwrap();
+        assert!(!svg.contains("<rect"));
+    }
 }
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use piet::{kurbo::Affine, RenderContext};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    element::Element,
+    error::{Error, Result},
+    geometry,
+    random::{random_id, random_integer},
+    Excalidraw,
+};
+
+/**
+ * `.excalidrawlib` 文件
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Library {
+    #[serde(rename = "type")]
+    pub type_field: String,
+    pub version: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub library_items: Vec<LibraryItem>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryItemStatus {
+    Published,
+    #[default]
+    Unpublished,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryItem {
+    pub id: String,
+    #[serde(default)]
+    pub status: LibraryItemStatus,
+    pub elements: Vec<Element>,
+    #[serde(default)]
+    pub created: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Self {
+            type_field: "excalidrawlib".to_string(),
+            version: 2,
+            source: Some("https://excalidraw.com".to_string()),
+            library_items: Vec::new(),
+            extra: Map::new(),
+        }
+    }
+}
+
+impl Library {
+    /**
+     * 解析 `.excalidrawlib`，version 1 的 `library` 字段会转换为 libraryItems
+     */
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut value: Value = serde_json::from_str(json)?;
+        if let Some(Value::Array(library)) =
+            value.as_object_mut().and_then(|map| map.remove("library"))
+        {
+            let items: Vec<Value> = library
+                .into_iter()
+                .map(|elements| {
+                    serde_json::json!({
+                        "id": random_id(),
+                        "status": "unpublished",
+                        "elements": elements,
+                        "created": 1,
+                    })
+                })
+                .collect();
+            value["libraryItems"] = Value::Array(items);
+            value["version"] = Value::from(2);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl LibraryItem {
+    /**
+     * 包含该条目所有元素的场景，可以用来导出 SVG / PDF
+     */
+    pub fn to_scene(&self) -> Excalidraw {
+        Excalidraw::new().with_elements(self.elements.clone())
+    }
+
+    /**
+     * 将条目缩放后居中绘制在 size × size 的区域内，用作缩略图
+     */
+    pub fn draw_thumbnail(
+        &self,
+        ctx: &mut impl RenderContext,
+        size: f64,
+        padding: f64,
+    ) -> Vec<Error> {
+        let scene = self.to_scene();
+        let rect = scene.get_canvas_size();
+        let content_size = (size - padding * 2.0).max(0.0);
+        let scale = match rect.width.max(rect.height) {
+            max if max > 0.0 => content_size / max,
+            _ => 1.0,
+        };
+        let _ = ctx.save();
+        ctx.transform(
+            Affine::translate((
+                (size - rect.width * scale) / 2.0,
+                (size - rect.height * scale) / 2.0,
+            )) * Affine::scale(scale),
+        );
+        let errors = scene.draw(ctx, 0.0);
+        let _ = ctx.restore();
+        errors
+    }
+}
+
+impl Excalidraw {
+    /**
+     * 把库中的条目放入场景，外接矩形的左上角位于 (x, y)。
+     * 元素和分组使用新的 id，绑定关系随之更新，返回新元素的 id
+     */
+    pub fn insert_library_item(&mut self, item: &LibraryItem, x: f64, y: f64) -> Vec<String> {
+        let elements: Vec<&Element> = item.elements.iter().filter(|e| !e.is_deleted).collect();
+        let ids: HashMap<&str, String> = elements
+            .iter()
+            .map(|element| (element.id.as_str(), random_id()))
+            .collect();
+        let mut group_ids: HashMap<String, String> = HashMap::new();
+        let rect = geometry::get_scene_bounds(&item.elements);
+
+        let mut inserted = Vec::with_capacity(elements.len());
+        for element in elements {
+            let mut element = element.clone();
+            let remap = |id: &str| ids.get(id).cloned();
+            element.id = ids[element.id.as_str()].clone();
+            element.x += x - rect.x;
+            element.y += y - rect.y;
+            element.seed = random_integer();
+            element.version_nonce = random_integer() as i64;
+            element.container_id = element.container_id.as_deref().and_then(remap);
+            element.frame_id = element.frame_id.as_deref().and_then(remap);
+            if let Some(bound_elements) = &mut element.bound_elements {
+                bound_elements.retain_mut(|bound| match remap(&bound.id) {
+                    Some(id) => {
+                        bound.id = id;
+                        true
+                    }
+                    None => false,
+                });
+            }
+            // 未建模的绑定与分组字段保存在 extra 中
+            for key in ["startBinding", "endBinding"] {
+                if let Some(binding) = element.extra.get_mut(key) {
+                    match binding
+                        .get("elementId")
+                        .and_then(Value::as_str)
+                        .and_then(remap)
+                    {
+                        Some(id) => binding["elementId"] = Value::String(id),
+                        None => *binding = Value::Null,
+                    }
+                }
+            }
+            if let Some(Value::Array(groups)) = element.extra.get_mut("groupIds") {
+                for group in groups.iter_mut() {
+                    if let Some(id) = group.as_str() {
+                        let new_id = group_ids.entry(id.to_string()).or_insert_with(random_id);
+                        *group = Value::String(new_id.clone());
+                    }
+                }
+            }
+            inserted.push(element.id.clone());
+            self.elements.push(element);
+        }
+        inserted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{BoundElement, ElementType};
+
+    #[test]
+    fn test_library_v1_and_v2() {
+        let v1 = r#"{"type":"excalidrawlib","version":1,"library":[[]]}"#;
+        let library = Library::from_json(v1).unwrap();
+        assert_eq!(library.version, 2);
+        assert_eq!(library.library_items.len(), 1);
+
+        let json = library.to_json().unwrap();
+        assert!(json.contains(r#""libraryItems":[{"#));
+        assert_eq!(Library::from_json(&json).unwrap(), library);
+    }
+
+    #[test]
+    fn test_insert_library_item() {
+        let mut rect = Element::rectangle(100.0, 100.0, 50.0, 50.0).with_id("rect");
+        rect.bound_elements = Some(vec![BoundElement {
+            id: "text".to_string(),
+            type_field: ElementType::Text,
+        }]);
+        rect.extra
+            .insert("groupIds".to_string(), serde_json::json!(["group"]));
+        let mut text = Element::text(110.0, 110.0, "hi").with_id("text");
+        text.container_id = Some("rect".to_string());
+        let item = LibraryItem {
+            id: "item".to_string(),
+            elements: vec![rect, text],
+            ..Default::default()
+        };
+
+        let mut scene = Excalidraw::new();
+        let ids = scene.insert_library_item(&item, 0.0, 0.0);
+        let rect = &scene.elements[0];
+        let text = &scene.elements[1];
+        assert!(!ids.contains(&"rect".to_string()));
+        assert_eq!(text.container_id.as_ref(), Some(&rect.id));
+        assert_eq!(rect.bound_elements.as_ref().unwrap()[0].id, text.id);
+        assert_ne!(rect.extra["groupIds"][0], "group");
+        // 外接矩形包含线宽等边距，左上角与目标位置对齐
+        let bounds = geometry::get_scene_bounds(&scene.elements);
+        assert!(bounds.x.abs() < 1e-9 && bounds.y.abs() < 1e-9);
+    }
+}
@@ -0,0 +1,285 @@
+pub use crate::element::{
+    Arrowhead, BoundElement, Element, ElementType, FillStyle, FixedSegment, FontFamily, ImageCrop,
+    ImageStatus, Roundness, RoundnessType, StrokeStyle, TextAlign, VerticalAlign,
+    DEFAULT_FONT_SIZE,
+};
+pub use crate::files::{BinaryFileData, BinaryFiles};
+pub use crate::point::Point;
+pub use crate::{AppState, Excalidraw, Rect};
+
+use crate::{
+    geometry::estimate_text_size,
+    random::{random_id, random_integer},
+};
+
+impl Element {
+    /**
+     * 使用 excalidraw 的默认样式创建元素，id 与 seed 随机生成
+     */
+    pub fn new(element_type: ElementType) -> Self {
+        Self {
+            id: random_id(),
+            element_type,
+            stroke_color: "#1e1e1e".to_string(),
+            background_color: "transparent".to_string(),
+            fill_style: FillStyle::Solid,
+            stroke_width: 2.0,
+            stroke_style: StrokeStyle::Solid,
+            roughness: 1.0,
+            opacity: 100,
+            seed: random_integer(),
+            version: 1,
+            version_nonce: random_integer() as i64,
+            ..Default::default()
+        }
+    }
+
+    pub fn rectangle(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self::new(ElementType::Rectangle)
+            .with_position(x, y)
+            .with_size(width, height)
+    }
+
+    pub fn diamond(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self::new(ElementType::Diamond)
+            .with_position(x, y)
+            .with_size(width, height)
+    }
+
+    pub fn ellipse(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self::new(ElementType::Ellipse)
+            .with_position(x, y)
+            .with_size(width, height)
+    }
+
+    /**
+     * points 为相对于 (x, y) 的坐标，宽高由 points 计算
+     */
+    pub fn line(x: f64, y: f64, points: Vec<Point>) -> Self {
+        Self::new(ElementType::Line)
+            .with_position(x, y)
+            .with_points(points)
+    }
+
+    pub fn arrow(x: f64, y: f64, points: Vec<Point>) -> Self {
+        Self::new(ElementType::Arrow)
+            .with_position(x, y)
+            .with_points(points)
+            .with_end_arrowhead(Some(Arrowhead::Arrow))
+    }
+
+    /**
+     * 宽度按字符数估算，需要精确尺寸时使用 with_size 修正
+     */
+    pub fn text(x: f64, y: f64, text: &str) -> Self {
+        let mut element = Self::new(ElementType::Text).with_position(x, y);
+        element.text = Some(text.to_string());
+        element.original_text = Some(text.to_string());
+        element.text_align = Some(TextAlign::Left);
+        element.vertical_align = Some(VerticalAlign::Top);
+        element.with_font(DEFAULT_FONT_SIZE, FontFamily::default())
+    }
+
+    pub fn image(x: f64, y: f64, width: f64, height: f64, file_id: &str) -> Self {
+        let mut element = Self::new(ElementType::Image)
+            .with_position(x, y)
+            .with_size(width, height);
+        element.file_id = Some(file_id.to_string());
+        element.status = Some(ImageStatus::Saved);
+        element.scale = Some((1.0, 1.0));
+        element
+    }
+
+    pub fn frame(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self::new(ElementType::Frame)
+            .with_position(x, y)
+            .with_size(width, height)
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = id.to_string();
+        self
+    }
+
+    pub fn with_position(mut self, x: f64, y: f64) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    pub fn with_size(mut self, width: f64, height: f64) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_angle(mut self, angle: f64) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    pub fn with_stroke_color(mut self, color: &str) -> Self {
+        self.stroke_color = color.to_string();
+        self
+    }
+
+    pub fn with_background_color(mut self, color: &str) -> Self {
+        self.background_color = color.to_string();
+        self
+    }
+
+    pub fn with_fill_style(mut self, fill_style: FillStyle) -> Self {
+        self.fill_style = fill_style;
+        self
+    }
+
+    pub fn with_stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = stroke_width;
+        self
+    }
+
+    pub fn with_stroke_style(mut self, stroke_style: StrokeStyle) -> Self {
+        self.stroke_style = stroke_style;
+        self
+    }
+
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: u8) -> Self {
+        self.opacity = opacity.min(100);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_roundness(mut self, roundness: Option<Roundness>) -> Self {
+        self.roundness = roundness;
+        self
+    }
+
+    /**
+     * 设置线条的点，并同步宽高
+     */
+    pub fn with_points(mut self, points: Vec<Point>) -> Self {
+        let (min_x, min_y, max_x, max_y) = points.iter().fold(
+            (0.0_f64, 0.0_f64, 0.0_f64, 0.0_f64),
+            |(min_x, min_y, max_x, max_y), point| {
+                (
+                    min_x.min(point.x),
+                    min_y.min(point.y),
+                    max_x.max(point.x),
+                    max_y.max(point.y),
+                )
+            },
+        );
+        self.width = max_x - min_x;
+        self.height = max_y - min_y;
+        self.points = Some(points);
+        self
+    }
+
+    pub fn with_start_arrowhead(mut self, arrowhead: Option<Arrowhead>) -> Self {
+        self.start_arrowhead = arrowhead;
+        self
+    }
+
+    pub fn with_end_arrowhead(mut self, arrowhead: Option<Arrowhead>) -> Self {
+        self.end_arrowhead = arrowhead;
+        self
+    }
+
+    /**
+     * 设置字体，并按新的字号重新估算文字宽高
+     */
+    pub fn with_font(mut self, font_size: f64, font_family: FontFamily) -> Self {
+        self.font_size = Some(font_size);
+        self.font_family = Some(font_family);
+        self.line_height = Some(font_family.default_line_height());
+        if let Some(text) = &self.text {
+            (self.width, self.height) =
+                estimate_text_size(text, font_size, self.get_line_height_in_px());
+        }
+        self
+    }
+
+    pub fn with_text_align(mut self, text_align: TextAlign) -> Self {
+        self.text_align = Some(text_align);
+        self
+    }
+
+    pub fn with_frame_id(mut self, frame_id: Option<&str>) -> Self {
+        self.frame_id = frame_id.map(str::to_string);
+        self
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+}
+
+impl Roundness {
+    pub fn new(type_field: RoundnessType) -> Self {
+        Self {
+            type_field,
+            value: None,
+        }
+    }
+}
+
+impl Excalidraw {
+    /**
+     * 创建空白场景，与 excalidraw 导出的默认值一致
+     */
+    pub fn new() -> Self {
+        Self {
+            type_field: "excalidraw".to_string(),
+            version: 2,
+            source: "https://excalidraw.com".to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_elements(mut self, elements: Vec<Element>) -> Self {
+        self.elements = elements;
+        self
+    }
+
+    pub fn add_element(&mut self, element: Element) {
+        self.elements.push(element);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_scene() {
+        let rect = Element::rectangle(10.0, 20.0, 100.0, 50.0)
+            .with_id("rect")
+            .with_background_color("#ffc9c9")
+            .with_roundness(Some(Roundness::new(RoundnessType::AdaptiveRadius)));
+        let arrow = Element::arrow(
+            0.0,
+            0.0,
+            vec![Point::new(0.0, 0.0), Point::new(80.0, -40.0)],
+        );
+        let scene = Excalidraw::new().with_elements(vec![rect, arrow]);
+
+        let arrow = &scene.elements[1];
+        assert_eq!((arrow.width, arrow.height), (80.0, 40.0));
+        assert_eq!(arrow.end_arrowhead, Some(Arrowhead::Arrow));
+        assert_eq!(arrow.id.len(), 21);
+        assert_ne!(arrow.id, Element::new(ElementType::Arrow).id);
+
+        let json = scene.to_json().unwrap();
+        assert_eq!(Excalidraw::from_json(&json).unwrap(), scene);
+    }
+}
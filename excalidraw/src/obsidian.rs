@@ -0,0 +1,377 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+/**
+ * 插件写入 compressed-json 时每 256 个字符分段一次
+ */
+const COMPRESSED_CHUNK_SIZE: usize = 256;
+const HEADER: &str = "---\n\nexcalidraw-plugin: parsed\ntags: [excalidraw]\n\n---\n==⚠  Switch to EXCALIDRAW VIEW in the MORE OPTIONS menu of this document. ⚠==\n\n\n# Excalidraw Data\n\n";
+
+/**
+ * 从 Obsidian Excalidraw 插件的 `.excalidraw.md` 中取出场景 JSON。
+ * `## Text Elements` 中修改过的文字会覆盖场景里对应文字元素的内容
+ */
+pub fn decode(markdown: &str) -> Result<String> {
+    let markdown = markdown.replace("\r\n", "\n");
+    let json = match find_code_block(&markdown, "compressed-json") {
+        Some(data) => {
+            let data: Vec<u8> = data.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+            decompress_from_base64(&data)
+                .ok_or_else(|| Error::decode("Invalid compressed-json block"))?
+        }
+        None => find_code_block(&markdown, "json")
+            .ok_or_else(|| Error::decode("No drawing found in markdown"))?
+            .to_string(),
+    };
+
+    let texts = parse_text_elements(&markdown);
+    if texts.is_empty() {
+        return Ok(json);
+    }
+    let mut scene: Value = serde_json::from_str(&json)?;
+    if let Some(Value::Array(elements)) = scene.get_mut("elements") {
+        for element in elements.iter_mut().filter(|e| e["type"] == "text") {
+            let Some(text) = element["id"].as_str().and_then(|id| texts.get(id)) else {
+                continue;
+            };
+            let original_text = element.get("originalText").or(element.get("text"));
+            if original_text.and_then(Value::as_str) != Some(text.as_str()) {
+                element["text"] = Value::String(text.clone());
+                element["originalText"] = Value::String(text.clone());
+            }
+        }
+    }
+    Ok(serde_json::to_string(&scene)?)
+}
+
+/**
+ * 生成 `.excalidraw.md`，compress 时与插件默认设置一致使用 LZ-string 压缩场景
+ */
+pub fn encode(json: &str, compress: bool) -> Result<String> {
+    let scene: Value = serde_json::from_str(json)?;
+    let mut markdown = String::from(HEADER);
+    markdown.push_str("## Text Elements\n");
+    if let Some(Value::Array(elements)) = scene.get("elements") {
+        let texts = elements
+            .iter()
+            .filter(|e| e["type"] == "text" && e["isDeleted"] != true);
+        for element in texts {
+            let text = element.get("originalText").or(element.get("text"));
+            if let (Some(id), Some(text)) = (element["id"].as_str(), text.and_then(Value::as_str)) {
+                markdown.push_str(&format!("{} ^{}\n\n", text, id));
+            }
+        }
+    }
+    markdown.push_str("%%\n## Drawing\n");
+    match compress {
+        true => {
+            let data = compress_to_base64(json);
+            let chunks: Vec<&str> = data
+                .as_bytes()
+                .chunks(COMPRESSED_CHUNK_SIZE)
+                // base64 只包含 ASCII 字符
+                .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+                .collect();
+            markdown.push_str(&format!(
+                "```compressed-json\n{}\n```\n",
+                chunks.join("\n\n")
+            ));
+        }
+        false => {
+            let json = serde_json::to_string_pretty(&scene)?;
+            markdown.push_str(&format!("```json\n{}\n```\n", json));
+        }
+    }
+    markdown.push_str("%%");
+    Ok(markdown)
+}
+
+fn find_code_block<'a>(markdown: &'a str, language: &str) -> Option<&'a str> {
+    let fence = format!("```{}\n", language);
+    let start = markdown.find(&fence)? + fence.len();
+    let end = markdown[start..].find("```")?;
+    Some(markdown[start..start + end].trim_end())
+}
+
+/**
+ * `## Text Elements` 中每个文字元素为 `文字 ^id`，文字可以跨行，元素之间用空行分隔
+ */
+fn parse_text_elements(markdown: &str) -> HashMap<String, String> {
+    let mut texts = HashMap::new();
+    let mut lines = markdown
+        .lines()
+        .skip_while(|line| {
+            line.trim_start_matches('#').trim() != "Text Elements" || !line.starts_with('#')
+        })
+        .skip(1);
+    let mut buffer: Vec<&str> = Vec::new();
+    for line in lines.by_ref() {
+        if line.starts_with("%%") || (line.starts_with('#') && !line.contains(" ^")) {
+            break;
+        }
+        match line.rsplit_once(" ^") {
+            Some((text, id))
+                if !id.is_empty()
+                    && id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+            {
+                buffer.push(text);
+                let text = buffer.join("\n");
+                texts.insert(id.to_string(), text.trim_matches('\n').to_string());
+                buffer.clear();
+            }
+            _ => buffer.push(line),
+        }
+    }
+    texts
+}
+
+/**
+ * 与 LZString.compressToBase64 一致，按 UTF-16 编码单元压缩
+ */
+pub(crate) fn compress_to_base64(input: &str) -> String {
+    let mut output = compress(input, 6, |value| BASE64_ALPHABET[value as usize] as char);
+    while output.len() % 4 != 0 {
+        output.push('=');
+    }
+    output
+}
+
+/**
+ * 与 LZString.decompressFromBase64 一致，数据不合法时返回 None
+ */
+pub(crate) fn decompress_from_base64(input: &[u8]) -> Option<String> {
+    let mut reverse = [0_u16; 256];
+    for (i, c) in BASE64_ALPHABET.iter().enumerate() {
+        reverse[*c as usize] = i as u16;
+    }
+    let values: Vec<u16> = input.iter().map(|c| reverse[*c as usize]).collect();
+    let units = decompress(&values, 32)?;
+    String::from_utf16(&units).ok()
+}
+
+struct BitWriter<F> {
+    bits_per_char: u32,
+    value: u16,
+    position: u32,
+    output: String,
+    to_char: F,
+}
+
+impl<F: Fn(u16) -> char> BitWriter<F> {
+    /**
+     * 低位在前写入 num_bits 位
+     */
+    fn write(&mut self, mut value: usize, num_bits: u32) {
+        for _ in 0..num_bits {
+            self.value = (self.value << 1) | (value & 1) as u16;
+            if self.position == self.bits_per_char - 1 {
+                self.position = 0;
+                self.output.push((self.to_char)(self.value));
+                self.value = 0;
+            } else {
+                self.position += 1;
+            }
+            value >>= 1;
+        }
+    }
+
+    fn flush(mut self) -> String {
+        loop {
+            self.value <<= 1;
+            if self.position == self.bits_per_char - 1 {
+                self.output.push((self.to_char)(self.value));
+                return self.output;
+            }
+            self.position += 1;
+        }
+    }
+}
+
+struct Compressor<F> {
+    dictionary: HashMap<Vec<u16>, usize>,
+    to_create: HashSet<Vec<u16>>,
+    enlarge_in: usize,
+    num_bits: u32,
+    writer: BitWriter<F>,
+}
+
+impl<F: Fn(u16) -> char> Compressor<F> {
+    fn decrease_enlarge_in(&mut self) {
+        self.enlarge_in -= 1;
+        if self.enlarge_in == 0 {
+            self.enlarge_in = 1 << self.num_bits;
+            self.num_bits += 1;
+        }
+    }
+
+    /**
+     * 输出 w 对应的编码，第一次出现的字符先写入字符本身
+     */
+    fn emit(&mut self, w: &Vec<u16>) {
+        if self.to_create.remove(w) {
+            let (marker, bits) = if w[0] < 256 { (0, 8) } else { (1, 16) };
+            self.writer.write(marker, self.num_bits);
+            self.writer.write(w[0] as usize, bits);
+            self.decrease_enlarge_in();
+        } else {
+            self.writer.write(self.dictionary[w], self.num_bits);
+        }
+        self.decrease_enlarge_in();
+    }
+}
+
+fn compress(input: &str, bits_per_char: u32, to_char: impl Fn(u16) -> char) -> String {
+    let mut compressor = Compressor {
+        dictionary: HashMap::new(),
+        to_create: HashSet::new(),
+        enlarge_in: 2,
+        num_bits: 2,
+        writer: BitWriter {
+            bits_per_char,
+            value: 0,
+            position: 0,
+            output: String::new(),
+            to_char,
+        },
+    };
+    // 0、1、2 为保留的编码
+    let mut dict_size = 3;
+    let mut w: Vec<u16> = Vec::new();
+    for c in input.encode_utf16() {
+        if let Entry::Vacant(entry) = compressor.dictionary.entry(vec![c]) {
+            entry.insert(dict_size);
+            compressor.to_create.insert(vec![c]);
+            dict_size += 1;
+        }
+        let mut wc = w.clone();
+        wc.push(c);
+        if compressor.dictionary.contains_key(&wc) {
+            w = wc;
+        } else {
+            compressor.emit(&w);
+            compressor.dictionary.insert(wc, dict_size);
+            dict_size += 1;
+            w = vec![c];
+        }
+    }
+    if !w.is_empty() {
+        compressor.emit(&w);
+    }
+    // 结束标记
+    compressor.writer.write(2, compressor.num_bits);
+    compressor.writer.flush()
+}
+
+fn decompress(input: &[u16], reset_value: u16) -> Option<Vec<u16>> {
+    let mut dictionary: Vec<Vec<u16>> = vec![vec![], vec![], vec![]];
+    let mut enlarge_in = 4_usize;
+    let mut num_bits = 3_u32;
+    let mut value = *input.first()?;
+    let mut position = reset_value;
+    let mut index = 1;
+
+    let mut read = |num_bits: u32, index: &mut usize| {
+        let mut bits = 0_usize;
+        for power in 0..num_bits {
+            let bit = value & position;
+            position >>= 1;
+            if position == 0 {
+                position = reset_value;
+                value = input.get(*index).copied().unwrap_or(0);
+                *index += 1;
+            }
+            if bit > 0 {
+                bits |= 1 << power;
+            }
+        }
+        bits
+    };
+
+    let c = match read(2, &mut index) {
+        0 => vec![read(8, &mut index) as u16],
+        1 => vec![read(16, &mut index) as u16],
+        2 => return Some(Vec::new()),
+        _ => return None,
+    };
+    dictionary.push(c.clone());
+    let mut w = c.clone();
+    let mut result = c;
+    loop {
+        if index > input.len() {
+            return None;
+        }
+        let mut c = read(num_bits, &mut index);
+        match c {
+            0 | 1 => {
+                let bits = if c == 0 { 8 } else { 16 };
+                dictionary.push(vec![read(bits, &mut index) as u16]);
+                c = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return Some(result),
+            _ => {}
+        }
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+        let entry = match dictionary.get(c) {
+            Some(entry) if c >= 3 => entry.clone(),
+            _ if c == dictionary.len() => {
+                let mut entry = w.clone();
+                entry.push(w[0]);
+                entry
+            }
+            _ => return None,
+        };
+        result.extend_from_slice(&entry);
+        let mut next = w;
+        next.push(entry[0]);
+        dictionary.push(next);
+        enlarge_in -= 1;
+        w = entry;
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Element, Excalidraw};
+
+    #[test]
+    fn test_lz_string() {
+        // LZString.compressToBase64("hello hello 你好")
+        let compressed = compress_to_base64("hello hello 你好");
+        assert_eq!(compressed, "BYUwNmD2AEoTgDeUL6aQ");
+        let decompressed = decompress_from_base64(compressed.as_bytes()).unwrap();
+        assert_eq!(decompressed, "hello hello 你好");
+    }
+
+    #[test]
+    fn test_obsidian_round_trip() {
+        let scene = Excalidraw::new().with_elements(vec![
+            Element::rectangle(0.0, 0.0, 100.0, 50.0),
+            Element::text(10.0, 10.0, "第一行\n第二行").with_id("text"),
+        ]);
+        for compress in [true, false] {
+            let markdown = scene.to_obsidian(compress).unwrap();
+            assert!(markdown.contains("## Text Elements\n第一行\n第二行 ^text\n\n%%"));
+            assert_eq!(Excalidraw::from_obsidian(&markdown).unwrap(), scene);
+
+            // 在 Obsidian 中修改的文字优先
+            let markdown = markdown.replace("第二行 ^text", "修改 ^text");
+            let edited = Excalidraw::from_obsidian(&markdown).unwrap();
+            assert_eq!(edited.elements[1].text.as_deref(), Some("第一行\n修改"));
+        }
+    }
+}
@@ -0,0 +1,1163 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    ops::RangeBounds,
+    rc::Rc,
+};
+
+use log::warn;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use piet::{
+    kurbo::{Affine, PathEl, Point, Rect, Shape, Size},
+    Color, Error, FixedGradient, FontFamily, HitTestPoint, HitTestPosition, Image, ImageFormat,
+    InterpolationMode, IntoBrush, LineCap, LineJoin, LineMetric, RenderContext, StrokeStyle, Text,
+    TextAlignment, TextAttribute, TextLayout, TextLayoutBuilder, TextStorage,
+};
+use subsetter::{subset, Profile};
+use ttf_parser::{name_id, Face, GlyphId};
+
+use crate::{
+    draw::Theme,
+    files::{load_fallback_font, load_system_font},
+    geometry::estimate_text_size,
+    svg::format_number,
+};
+
+/**
+ * 曲线转换为路径时的精度
+ */
+const PATH_TOLERANCE: f64 = 0.1;
+/**
+ * 内置 Helvetica 的基线位置（相对字号）
+ */
+const BUILTIN_FONT_ASCENT: f64 = 0.718;
+const COMPRESSION_LEVEL: u8 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfOptions {
+    pub padding: f64,
+    /**
+     * 是否绘制 viewBackgroundColor 背景
+     */
+    pub background: bool,
+    /**
+     * 场景中有 frame 时每个 frame 单独一页，frame 之外的元素另起一页，否则整个场景一页
+     */
+    pub frames_as_pages: bool,
+    /**
+     * 深色模式与 excalidraw 的 exportWithDarkMode 一致，图片保持原样
+     */
+    pub theme: Theme,
+    /**
+     * 以字体名称为 key 的字体文件（ttf / otf），找不到时从系统字体中查找
+     */
+    pub fonts: HashMap<String, Vec<u8>>,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            padding: 10.0,
+            background: true,
+            frames_as_pages: true,
+            theme: Theme::Light,
+            fonts: HashMap::new(),
+        }
+    }
+}
+
+/**
+ * 所有页面共用的字体、图片与透明度资源
+ */
+#[derive(Default)]
+struct Resources {
+    custom_fonts: HashMap<String, Vec<u8>>,
+    fonts: Vec<PdfFont>,
+    font_index: HashMap<String, usize>,
+    images: Vec<PdfImageData>,
+    alphas: BTreeMap<(bool, u8), usize>,
+}
+
+struct PdfFont {
+    base_font: String,
+    /**
+     * 为 None 时使用 PDF 内置的 Helvetica
+     */
+    data: Option<FontData>,
+    /**
+     * 用到的字形及其对应的字符，用于生成宽度表和 ToUnicode
+     */
+    glyphs: BTreeMap<u16, char>,
+}
+
+struct FontData {
+    bytes: Vec<u8>,
+    is_cff: bool,
+}
+
+struct PdfImageData {
+    width: usize,
+    height: usize,
+    rgb: Vec<u8>,
+    alpha: Option<Vec<u8>>,
+}
+
+impl Resources {
+    /**
+     * 按名称查找字体，依次尝试传入的字体文件、系统字体和通用字体
+     */
+    fn resolve_font(&mut self, name: &str) -> usize {
+        if let Some(index) = self.font_index.get(name) {
+            return *index;
+        }
+        let data = self
+            .custom_fonts
+            .get(name)
+            .cloned()
+            .filter(|bytes| Face::parse(bytes, 0).is_ok())
+            .or_else(|| load_system_font(name))
+            .or_else(|| load_fallback_font(name));
+        let base_font = data
+            .as_ref()
+            .and_then(|bytes| Face::parse(bytes, 0).ok())
+            .and_then(|face| {
+                face.names()
+                    .into_iter()
+                    .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+                    .find_map(|name| name.to_string())
+            })
+            .unwrap_or_else(|| name.to_string());
+        if data.is_none() {
+            warn!(
+                "No embeddable font found for {}, falling back to built-in Helvetica",
+                name
+            );
+        }
+        let font = PdfFont {
+            base_font: match data {
+                Some(_) => sanitize_name(&base_font),
+                None => "Helvetica".to_string(),
+            },
+            data: data.map(|bytes| FontData {
+                is_cff: bytes.starts_with(b"OTTO"),
+                bytes,
+            }),
+            glyphs: BTreeMap::new(),
+        };
+        self.fonts.push(font);
+        self.font_index
+            .insert(name.to_string(), self.fonts.len() - 1);
+        self.fonts.len() - 1
+    }
+
+    fn alpha_state(&mut self, stroke: bool, alpha: u8) -> usize {
+        let next = self.alphas.len();
+        *self.alphas.entry((stroke, alpha)).or_insert(next)
+    }
+}
+
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+/**
+ * 多页 PDF 文档，每页由一个 PdfRenderContext 绘制
+ */
+pub(crate) struct PdfDocument {
+    resources: Rc<RefCell<Resources>>,
+    pages: Vec<(f64, f64, String)>,
+}
+
+impl PdfDocument {
+    pub(crate) fn new(fonts: &HashMap<String, Vec<u8>>) -> Self {
+        Self {
+            resources: Rc::new(RefCell::new(Resources {
+                custom_fonts: fonts.clone(),
+                ..Default::default()
+            })),
+            pages: Vec::new(),
+        }
+    }
+
+    pub(crate) fn new_page(&self, width: f64, height: f64) -> PdfRenderContext {
+        PdfRenderContext::new(self.resources.clone(), width, height)
+    }
+
+    pub(crate) fn add_page(&mut self, page: PdfRenderContext) {
+        self.pages.push((page.width, page.height, page.content));
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        let resources = self.resources.borrow();
+        let mut writer = PdfWriter::default();
+        let catalog_id = writer.alloc();
+        let pages_id = writer.alloc();
+        let resources_id = writer.alloc();
+
+        let mut fonts = String::new();
+        for (index, font) in resources.fonts.iter().enumerate() {
+            let id = write_font(&mut writer, font);
+            let _ = write!(fonts, "/F{} {} 0 R ", index, id);
+        }
+        let mut images = String::new();
+        for (index, image) in resources.images.iter().enumerate() {
+            let id = write_image(&mut writer, image);
+            let _ = write!(images, "/Im{} {} 0 R ", index, id);
+        }
+        let mut alphas = String::new();
+        for ((stroke, alpha), index) in &resources.alphas {
+            let _ = write!(
+                alphas,
+                "/GS{} << /{} {} >> ",
+                index,
+                if *stroke { "CA" } else { "ca" },
+                format_number(*alpha as f64 / 255.0)
+            );
+        }
+        writer.set(
+            resources_id,
+            format!(
+                "<< /Font << {}>> /XObject << {}>> /ExtGState << {}>> >>",
+                fonts, images, alphas
+            )
+            .into_bytes(),
+        );
+
+        let mut kids = String::new();
+        for (width, height, content) in &self.pages {
+            let content_id = writer.alloc();
+            let content = compress_to_vec_zlib(content.as_bytes(), COMPRESSION_LEVEL);
+            writer.set(content_id, stream("/Filter /FlateDecode", &content));
+            let page_id = writer.alloc();
+            writer.set(
+                page_id,
+                format!(
+                    "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources {} 0 R /Contents {} 0 R >>",
+                    pages_id,
+                    format_number(*width),
+                    format_number(*height),
+                    resources_id,
+                    content_id
+                )
+                .into_bytes(),
+            );
+            let _ = write!(kids, "{} 0 R ", page_id);
+        }
+        writer.set(
+            pages_id,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.trim_end(),
+                self.pages.len()
+            )
+            .into_bytes(),
+        );
+        writer.set(
+            catalog_id,
+            format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id).into_bytes(),
+        );
+        writer.finish(catalog_id)
+    }
+}
+
+/**
+ * 嵌入只包含用到字形的子集字体，字符按字形 id 编码（Identity-H）
+ */
+fn write_font(writer: &mut PdfWriter, font: &PdfFont) -> usize {
+    let font_id = writer.alloc();
+    let (data, face) = match font
+        .data
+        .as_ref()
+        .and_then(|data| Some((data, Face::parse(&data.bytes, 0).ok()?)))
+    {
+        Some(font_data) => font_data,
+        None => {
+            writer.set(
+                font_id,
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    font.base_font
+                )
+                .into_bytes(),
+            );
+            return font_id;
+        }
+    };
+    let scale = 1000.0 / face.units_per_em() as f64;
+    let to_pdf_units = |value: i16| format_number(value as f64 * scale);
+
+    // 子集保留原有的字形 id，.notdef 必须保留
+    let glyphs: Vec<u16> = std::iter::once(0)
+        .chain(font.glyphs.keys().copied())
+        .collect();
+    let (bytes, base_font) = match subset(&data.bytes, 0, Profile::pdf(&glyphs)) {
+        Ok(bytes) => (
+            Cow::Owned(bytes),
+            format!("{}+{}", get_subset_tag(&glyphs), font.base_font),
+        ),
+        Err(e) => {
+            warn!("Failed to subset font {}: {}", font.base_font, e);
+            (Cow::Borrowed(&data.bytes), font.base_font.clone())
+        }
+    };
+    let file_id = writer.alloc();
+    let compressed = compress_to_vec_zlib(&bytes, COMPRESSION_LEVEL);
+    let file_dict = match data.is_cff {
+        true => "/Subtype /OpenType /Filter /FlateDecode".to_string(),
+        false => format!("/Length1 {} /Filter /FlateDecode", bytes.len()),
+    };
+    writer.set(file_id, stream(&file_dict, &compressed));
+
+    let bbox = face.global_bounding_box();
+    let descriptor_id = writer.alloc();
+    writer.set(
+        descriptor_id,
+        format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags {} /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /{} {} 0 R >>",
+            base_font,
+            if face.is_monospaced() { 33 } else { 32 },
+            to_pdf_units(bbox.x_min),
+            to_pdf_units(bbox.y_min),
+            to_pdf_units(bbox.x_max),
+            to_pdf_units(bbox.y_max),
+            to_pdf_units(face.ascender()),
+            to_pdf_units(face.descender()),
+            to_pdf_units(face.capital_height().unwrap_or(face.ascender())),
+            if data.is_cff { "FontFile3" } else { "FontFile2" },
+            file_id
+        )
+        .into_bytes(),
+    );
+
+    let mut widths = String::new();
+    for glyph in font.glyphs.keys() {
+        let advance = face.glyph_hor_advance(GlyphId(*glyph)).unwrap_or(0);
+        let _ = write!(
+            widths,
+            "{} [{}] ",
+            glyph,
+            format_number(advance as f64 * scale)
+        );
+    }
+    let cid_font_id = writer.alloc();
+    writer.set(
+        cid_font_id,
+        format!(
+            "<< /Type /Font /Subtype /{} /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R /W [{}]{} >>",
+            if data.is_cff { "CIDFontType0" } else { "CIDFontType2" },
+            base_font,
+            descriptor_id,
+            widths.trim_end(),
+            if data.is_cff { "" } else { " /CIDToGIDMap /Identity" }
+        )
+        .into_bytes(),
+    );
+
+    let to_unicode_id = writer.alloc();
+    writer.set(
+        to_unicode_id,
+        stream("", get_to_unicode(&font.glyphs).as_bytes()),
+    );
+    writer.set(
+        font_id,
+        format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+            base_font, cid_font_id, to_unicode_id
+        )
+        .into_bytes(),
+    );
+    font_id
+}
+
+/**
+ * 子集字体名称前的 6 位大写字母标记，由用到的字形决定
+ */
+fn get_subset_tag(glyphs: &[u16]) -> String {
+    let mut hash = glyphs.iter().fold(0x811c9dc5u32, |hash, glyph| {
+        (hash ^ *glyph as u32).wrapping_mul(0x01000193)
+    });
+    (0..6)
+        .map(|_| {
+            let c = (b'A' + (hash % 26) as u8) as char;
+            hash /= 26;
+            c
+        })
+        .collect()
+}
+
+/**
+ * 字形 id 到 Unicode 的映射，使 PDF 中的文字可以被复制和搜索
+ */
+fn get_to_unicode(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n/CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let glyphs: Vec<_> = glyphs.iter().collect();
+    // 每个 bfchar 块最多 100 项
+    for chunk in glyphs.chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfchar", chunk.len());
+        for (glyph, c) in chunk {
+            let mut units = [0; 2];
+            let unicode: String = c
+                .encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            let _ = writeln!(cmap, "<{:04X}> <{}>", glyph, unicode);
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+fn write_image(writer: &mut PdfWriter, image: &PdfImageData) -> usize {
+    let smask = image.alpha.as_ref().map(|alpha| {
+        let id = writer.alloc();
+        let dict = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8 /Filter /FlateDecode",
+            image.width, image.height
+        );
+        writer.set(id, stream(&dict, &compress_to_vec_zlib(alpha, COMPRESSION_LEVEL)));
+        format!(" /SMask {} 0 R", id)
+    });
+    let id = writer.alloc();
+    let dict = format!(
+        "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode{}",
+        image.width,
+        image.height,
+        smask.unwrap_or_default()
+    );
+    writer.set(
+        id,
+        stream(&dict, &compress_to_vec_zlib(&image.rgb, COMPRESSION_LEVEL)),
+    );
+    id
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let dict = match dict {
+        "" => format!("<< /Length {} >>", data.len()),
+        _ => format!("<< {} /Length {} >>", dict, data.len()),
+    };
+    let mut bytes = format!("{}\nstream\n", dict).into_bytes();
+    bytes.extend_from_slice(data);
+    bytes.extend_from_slice(b"\nendstream");
+    bytes
+}
+
+/**
+ * 按对象编号收集对象，最后写出交叉引用表
+ */
+#[derive(Default)]
+struct PdfWriter {
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfWriter {
+    fn alloc(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, object: Vec<u8>) {
+        self.objects[id - 1] = object;
+    }
+
+    fn finish(self, root: usize) -> Vec<u8> {
+        let mut bytes = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (index, object) in self.objects.iter().enumerate() {
+            offsets.push(bytes.len());
+            bytes.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            bytes.extend_from_slice(object);
+            bytes.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = bytes.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            xref
+        );
+        bytes.extend_from_slice(trailer.as_bytes());
+        bytes
+    }
+}
+
+/**
+ * 将 piet 的绘制指令输出为 PDF 内容流，坐标系翻转为 y 轴向下以与位图一致
+ */
+pub(crate) struct PdfRenderContext {
+    resources: Rc<RefCell<Resources>>,
+    text: PdfText,
+    width: f64,
+    height: f64,
+    content: String,
+    transform: Affine,
+    stack: Vec<Affine>,
+}
+
+impl PdfRenderContext {
+    fn new(resources: Rc<RefCell<Resources>>, width: f64, height: f64) -> Self {
+        Self {
+            text: PdfText(resources.clone()),
+            resources,
+            width,
+            height,
+            content: format!("1 0 0 -1 0 {} cm\n", format_number(height)),
+            transform: Affine::IDENTITY,
+            stack: Vec::new(),
+        }
+    }
+
+    fn write_path(&mut self, shape: impl Shape) {
+        let mut start = Point::ZERO;
+        let mut last = Point::ZERO;
+        for element in shape.path_elements(PATH_TOLERANCE) {
+            match element {
+                PathEl::MoveTo(p) => {
+                    let _ = writeln!(
+                        self.content,
+                        "{} {} m",
+                        format_number(p.x),
+                        format_number(p.y)
+                    );
+                    start = p;
+                    last = p;
+                }
+                PathEl::LineTo(p) => {
+                    let _ = writeln!(
+                        self.content,
+                        "{} {} l",
+                        format_number(p.x),
+                        format_number(p.y)
+                    );
+                    last = p;
+                }
+                PathEl::QuadTo(q, p) => {
+                    // PDF 只有三次贝塞尔曲线
+                    let c1 = last + (q - last) * (2.0 / 3.0);
+                    let c2 = p + (q - p) * (2.0 / 3.0);
+                    self.write_curve(c1, c2, p);
+                    last = p;
+                }
+                PathEl::CurveTo(c1, c2, p) => {
+                    self.write_curve(c1, c2, p);
+                    last = p;
+                }
+                PathEl::ClosePath => {
+                    self.content.push_str("h\n");
+                    last = start;
+                }
+            }
+        }
+    }
+
+    fn write_curve(&mut self, c1: Point, c2: Point, p: Point) {
+        let _ = writeln!(
+            self.content,
+            "{} {} {} {} {} {} c",
+            format_number(c1.x),
+            format_number(c1.y),
+            format_number(c2.x),
+            format_number(c2.y),
+            format_number(p.x),
+            format_number(p.y)
+        );
+    }
+
+    fn write_color(&mut self, color: &Color, stroke: bool) {
+        let (r, g, b, a) = color.as_rgba8();
+        if a < 255 {
+            let index = self.resources.borrow_mut().alpha_state(stroke, a);
+            let _ = writeln!(self.content, "/GS{} gs", index);
+        }
+        let _ = writeln!(
+            self.content,
+            "{} {} {} {}",
+            format_number(r as f64 / 255.0),
+            format_number(g as f64 / 255.0),
+            format_number(b as f64 / 255.0),
+            if stroke { "RG" } else { "rg" }
+        );
+    }
+
+    fn write_stroke_style(&mut self, width: f64, style: &StrokeStyle) {
+        let _ = writeln!(self.content, "{} w", format_number(width));
+        match style.line_join {
+            LineJoin::Miter { limit } => {
+                let _ = writeln!(self.content, "0 j {} M", format_number(limit));
+            }
+            LineJoin::Round => self.content.push_str("1 j\n"),
+            LineJoin::Bevel => self.content.push_str("2 j\n"),
+        }
+        match style.line_cap {
+            LineCap::Butt => self.content.push_str("0 J\n"),
+            LineCap::Round => self.content.push_str("1 J\n"),
+            LineCap::Square => self.content.push_str("2 J\n"),
+        }
+        if !style.dash_pattern.is_empty() {
+            let dashes: Vec<String> = style
+                .dash_pattern
+                .iter()
+                .map(|d| format_number(*d))
+                .collect();
+            let _ = writeln!(
+                self.content,
+                "[{}] {} d",
+                dashes.join(" "),
+                format_number(style.dash_offset)
+            );
+        }
+    }
+
+    fn fill_with_rule(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>, operator: &str) {
+        let color = brush.make_brush(self, || shape.bounding_box()).into_owned();
+        self.content.push_str("q\n");
+        self.write_color(&color, false);
+        self.write_path(shape);
+        let _ = writeln!(self.content, "{}\nQ", operator);
+    }
+
+    fn write_image(&mut self, image: &PdfImage, src_rect: Rect, dst_rect: Rect) {
+        // 先裁剪到目标区域，再把整张图缩放到 src_rect 与 dst_rect 重合的位置
+        let scale_x = dst_rect.width() / src_rect.width().max(f64::EPSILON);
+        let scale_y = dst_rect.height() / src_rect.height().max(f64::EPSILON);
+        let x = dst_rect.x0 - src_rect.x0 * scale_x;
+        let y = dst_rect.y0 - src_rect.y0 * scale_y;
+        let width = image.width as f64 * scale_x;
+        let height = image.height as f64 * scale_y;
+        self.content.push_str("q\n");
+        self.write_path(dst_rect);
+        self.content.push_str("W n\n");
+        // 图片空间的 y 轴向上，需要再翻转一次
+        let _ = writeln!(
+            self.content,
+            "{} 0 0 {} {} {} cm\n/Im{} Do\nQ",
+            format_number(width),
+            format_number(-height),
+            format_number(x),
+            format_number(y + height),
+            image.index
+        );
+    }
+}
+
+impl RenderContext for PdfRenderContext {
+    type Brush = Color;
+    type Image = PdfImage;
+    type Text = PdfText;
+    type TextLayout = PdfTextLayout;
+
+    fn status(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn solid_brush(&mut self, color: Color) -> Self::Brush {
+        color
+    }
+
+    fn gradient(&mut self, _gradient: impl Into<FixedGradient>) -> Result<Self::Brush, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn clear(&mut self, region: impl Into<Option<Rect>>, color: Color) {
+        let rect = region
+            .into()
+            .unwrap_or_else(|| Rect::new(0.0, 0.0, self.width, self.height));
+        // clear 不受当前 transform 影响
+        let transform = self.transform;
+        self.content.push_str("q\n");
+        self.transform(transform.inverse());
+        self.fill(rect, &color);
+        self.content.push_str("Q\n");
+        self.transform = transform;
+    }
+
+    fn stroke(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>, width: f64) {
+        self.stroke_styled(shape, brush, width, &StrokeStyle::default());
+    }
+
+    fn stroke_styled(
+        &mut self,
+        shape: impl Shape,
+        brush: &impl IntoBrush<Self>,
+        width: f64,
+        style: &StrokeStyle,
+    ) {
+        let color = brush.make_brush(self, || shape.bounding_box()).into_owned();
+        self.content.push_str("q\n");
+        self.write_color(&color, true);
+        self.write_stroke_style(width, style);
+        self.write_path(shape);
+        self.content.push_str("S\nQ\n");
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
+        self.fill_with_rule(shape, brush, "f");
+    }
+
+    fn fill_even_odd(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
+        self.fill_with_rule(shape, brush, "f*");
+    }
+
+    fn clip(&mut self, shape: impl Shape) {
+        self.write_path(shape);
+        self.content.push_str("W n\n");
+    }
+
+    fn text(&mut self) -> &mut Self::Text {
+        &mut self.text
+    }
+
+    fn draw_text(&mut self, layout: &Self::TextLayout, pos: impl Into<Point>) {
+        let pos = pos.into();
+        self.content.push_str("q\n");
+        self.write_color(&layout.color, false);
+        // 文字矩阵再翻转一次，使字形保持正向
+        let _ = writeln!(
+            self.content,
+            "BT\n/F{} {} Tf\n1 0 0 -1 {} {} Tm\n{} Tj\nET\nQ",
+            layout.font,
+            format_number(layout.font_size),
+            format_number(pos.x),
+            format_number(pos.y + layout.ascent),
+            layout.encoded
+        );
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.stack.push(self.transform);
+        self.content.push_str("q\n");
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        self.transform = self.stack.pop().ok_or(Error::StackUnbalance)?;
+        self.content.push_str("Q\n");
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        self.transform *= transform;
+        let coeffs: Vec<String> = transform
+            .as_coeffs()
+            .iter()
+            .map(|c| format_number(*c))
+            .collect();
+        let _ = writeln!(self.content, "{} cm", coeffs.join(" "));
+    }
+
+    fn make_image_with_stride(
+        &mut self,
+        width: usize,
+        height: usize,
+        stride: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<Self::Image, Error> {
+        let bytes_per_pixel = format.bytes_per_pixel();
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        let mut alpha = Vec::with_capacity(width * height);
+        for row in 0..height {
+            let start = row * stride;
+            let row = buf
+                .get(start..start + width * bytes_per_pixel)
+                .ok_or(Error::InvalidInput)?;
+            for pixel in row.chunks_exact(bytes_per_pixel) {
+                match format {
+                    ImageFormat::Grayscale => {
+                        rgb.extend([pixel[0], pixel[0], pixel[0]]);
+                        alpha.push(255);
+                    }
+                    ImageFormat::Rgb => {
+                        rgb.extend_from_slice(pixel);
+                        alpha.push(255);
+                    }
+                    ImageFormat::RgbaSeparate => {
+                        rgb.extend_from_slice(&pixel[..3]);
+                        alpha.push(pixel[3]);
+                    }
+                    ImageFormat::RgbaPremul => {
+                        // PDF 的 SMask 使用非预乘的 alpha
+                        let a = pixel[3] as u16;
+                        let unpremultiply = |channel: u8| match a {
+                            0 => 0,
+                            _ => ((channel as u16 * 255 + a / 2) / a).min(255) as u8,
+                        };
+                        rgb.extend([
+                            unpremultiply(pixel[0]),
+                            unpremultiply(pixel[1]),
+                            unpremultiply(pixel[2]),
+                        ]);
+                        alpha.push(pixel[3]);
+                    }
+                    _ => return Err(Error::NotSupported),
+                }
+            }
+        }
+        let mut resources = self.resources.borrow_mut();
+        resources.images.push(PdfImageData {
+            width,
+            height,
+            rgb,
+            alpha: alpha.iter().any(|a| *a < 255).then_some(alpha),
+        });
+        Ok(PdfImage {
+            index: resources.images.len() - 1,
+            width,
+            height,
+        })
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &Self::Image,
+        dst_rect: impl Into<Rect>,
+        _interp: InterpolationMode,
+    ) {
+        let src_rect = Rect::new(0.0, 0.0, image.width as f64, image.height as f64);
+        self.write_image(image, src_rect, dst_rect.into());
+    }
+
+    fn draw_image_area(
+        &mut self,
+        image: &Self::Image,
+        src_rect: impl Into<Rect>,
+        dst_rect: impl Into<Rect>,
+        _interp: InterpolationMode,
+    ) {
+        self.write_image(image, src_rect.into(), dst_rect.into());
+    }
+
+    fn capture_image_area(&mut self, _src_rect: impl Into<Rect>) -> Result<Self::Image, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, _blur_radius: f64, brush: &impl IntoBrush<Self>) {
+        self.fill(rect, brush);
+    }
+
+    fn current_transform(&self) -> Affine {
+        self.transform
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PdfImage {
+    index: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Image for PdfImage {
+    fn size(&self) -> Size {
+        Size::new(self.width as f64, self.height as f64)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct PdfText(Rc<RefCell<Resources>>);
+
+impl Text for PdfText {
+    type TextLayout = PdfTextLayout;
+    type TextLayoutBuilder = PdfTextLayoutBuilder;
+
+    fn font_family(&mut self, family_name: &str) -> Option<FontFamily> {
+        // 字体在 build 时才解析，找不到时回退到内置字体
+        Some(FontFamily::new_unchecked(family_name))
+    }
+
+    fn load_font(&mut self, _data: &[u8]) -> Result<FontFamily, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn new_text_layout(&mut self, text: impl TextStorage) -> Self::TextLayoutBuilder {
+        PdfTextLayoutBuilder {
+            resources: self.0.clone(),
+            text: text.as_str().to_string(),
+            font_family: FontFamily::SANS_SERIF,
+            font_size: piet::util::DEFAULT_FONT_SIZE,
+            color: piet::util::DEFAULT_TEXT_COLOR,
+        }
+    }
+}
+
+pub(crate) struct PdfTextLayoutBuilder {
+    resources: Rc<RefCell<Resources>>,
+    text: String,
+    font_family: FontFamily,
+    font_size: f64,
+    color: Color,
+}
+
+impl TextLayoutBuilder for PdfTextLayoutBuilder {
+    type Out = PdfTextLayout;
+
+    fn max_width(self, _width: f64) -> Self {
+        self
+    }
+
+    fn alignment(self, _alignment: TextAlignment) -> Self {
+        self
+    }
+
+    fn default_attribute(mut self, attribute: impl Into<TextAttribute>) -> Self {
+        match attribute.into() {
+            TextAttribute::FontFamily(font_family) => self.font_family = font_family,
+            TextAttribute::FontSize(font_size) => self.font_size = font_size,
+            TextAttribute::TextColor(color) => self.color = color,
+            _ => {}
+        }
+        self
+    }
+
+    fn range_attribute(
+        self,
+        _range: impl RangeBounds<usize>,
+        _attribute: impl Into<TextAttribute>,
+    ) -> Self {
+        self
+    }
+
+    fn build(self) -> Result<Self::Out, Error> {
+        let mut resources = self.resources.borrow_mut();
+        let font = resources.resolve_font(self.font_family.name());
+        let pdf_font = &mut resources.fonts[font];
+        let face = pdf_font
+            .data
+            .as_ref()
+            .and_then(|data| Face::parse(&data.bytes, 0).ok());
+        let layout = match face {
+            Some(face) => {
+                let scale = self.font_size / face.units_per_em() as f64;
+                let mut encoded = String::from("<");
+                let mut width = 0.0;
+                for c in self.text.chars() {
+                    let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+                    width += face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * scale;
+                    let _ = write!(encoded, "{:04X}", glyph.0);
+                    pdf_font.glyphs.entry(glyph.0).or_insert(c);
+                }
+                encoded.push('>');
+                PdfTextLayout {
+                    size: Size::new(
+                        width,
+                        (face.ascender() as f64 - face.descender() as f64) * scale,
+                    ),
+                    ascent: face.ascender() as f64 * scale,
+                    encoded,
+                    text: self.text,
+                    font,
+                    font_size: self.font_size,
+                    color: self.color,
+                }
+            }
+            None => {
+                if !self.text.chars().all(is_builtin_char) {
+                    warn!(
+                        "Characters in {:?} are not supported by built-in Helvetica and are replaced with ?",
+                        self.text
+                    );
+                }
+                let (width, _) = estimate_text_size(&self.text, self.font_size, 0.0);
+                PdfTextLayout {
+                    size: Size::new(width, self.font_size),
+                    ascent: self.font_size * BUILTIN_FONT_ASCENT,
+                    encoded: encode_builtin_text(&self.text),
+                    text: self.text,
+                    font,
+                    font_size: self.font_size,
+                    color: self.color,
+                }
+            }
+        };
+        Ok(layout)
+    }
+}
+
+/**
+ * 内置字体只支持 WinAnsi，超出范围的字符替换为 ?
+ */
+fn encode_builtin_text(text: &str) -> String {
+    let mut encoded = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                encoded.push('\\');
+                encoded.push(c);
+            }
+            ' '..='~' => encoded.push(c),
+            c if is_builtin_char(c) => {
+                let _ = write!(encoded, "\\{:03o}", c as u32);
+            }
+            _ => encoded.push('?'),
+        }
+    }
+    encoded.push(')');
+    encoded
+}
+
+fn is_builtin_char(c: char) -> bool {
+    matches!(c, ' '..='~' | '\u{a0}'..='\u{ff}')
+}
+
+/**
+ * 单行文字，字形已按字体编码
+ */
+#[derive(Debug, Clone)]
+pub(crate) struct PdfTextLayout {
+    text: String,
+    encoded: String,
+    font: usize,
+    font_size: f64,
+    color: Color,
+    size: Size,
+    ascent: f64,
+}
+
+impl TextLayout for PdfTextLayout {
+    fn size(&self) -> Size {
+        self.size
+    }
+
+    fn trailing_whitespace_width(&self) -> f64 {
+        0.0
+    }
+
+    fn image_bounds(&self) -> Rect {
+        self.size.to_rect()
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn line_text(&self, line_number: usize) -> Option<&str> {
+        (line_number == 0).then_some(self.text.as_str())
+    }
+
+    fn line_metric(&self, line_number: usize) -> Option<LineMetric> {
+        (line_number == 0).then(|| LineMetric {
+            start_offset: 0,
+            end_offset: self.text.len(),
+            trailing_whitespace: self.text.len() - self.text.trim_end().len(),
+            baseline: self.ascent,
+            height: self.size.height,
+            y_offset: 0.0,
+        })
+    }
+
+    fn line_count(&self) -> usize {
+        1
+    }
+
+    fn hit_test_point(&self, _point: Point) -> HitTestPoint {
+        HitTestPoint::default()
+    }
+
+    fn hit_test_text_position(&self, _idx: usize) -> HitTestPosition {
+        HitTestPosition::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::Element, Excalidraw};
+
+    fn count(bytes: &[u8], pattern: &str) -> usize {
+        String::from_utf8_lossy(bytes).matches(pattern).count()
+    }
+
+    #[test]
+    fn test_pdf_page_per_frame() {
+        let scene = Excalidraw::new().with_elements(vec![
+            Element::frame(0.0, 0.0, 200.0, 100.0).with_id("frame-1"),
+            Element::rectangle(10.0, 10.0, 50.0, 50.0).with_frame_id(Some("frame-1")),
+            Element::frame(300.0, 0.0, 100.0, 100.0).with_id("frame-2"),
+            Element::text(310.0, 10.0, "(hi)").with_frame_id(Some("frame-2")),
+        ]);
+        let pdf = scene.to_pdf(&PdfOptions::default()).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.7"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        assert_eq!(count(&pdf, "/Type /Page "), 2);
+        assert_eq!(count(&pdf, "/Count 2"), 1);
+
+        let options = PdfOptions {
+            frames_as_pages: false,
+            ..Default::default()
+        };
+        let pdf = scene.to_pdf(&options).unwrap();
+        assert_eq!(count(&pdf, "/Type /Page "), 1);
+    }
+
+    #[test]
+    fn test_pdf_unframed_elements_page() {
+        let scene = Excalidraw::new().with_elements(vec![
+            Element::frame(0.0, 0.0, 200.0, 100.0).with_id("frame-1"),
+            Element::rectangle(10.0, 10.0, 50.0, 50.0).with_frame_id(Some("frame-1")),
+            Element::ellipse(500.0, 500.0, 40.0, 40.0),
+            Element::rectangle(600.0, 500.0, 40.0, 40.0).with_frame_id(Some("missing")),
+        ]);
+        let pdf = scene.to_pdf(&PdfOptions::default()).unwrap();
+        assert_eq!(count(&pdf, "/Type /Page "), 2);
+        // 最后一页只包含 frame 之外的元素：宽约 140 加上 padding
+        let pdf = String::from_utf8_lossy(&pdf);
+        let width: f64 = pdf
+            .split("/MediaBox [0 0 ")
+            .nth(2)
+            .and_then(|rest| rest.split(' ').next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((160.0..200.0).contains(&width), "{}", width);
+    }
+
+    #[test]
+    fn test_pdf_font_subset() {
+        let font = include_bytes!("../tests/fixtures/excalidraw-test.ttf").to_vec();
+        let scene = Excalidraw::new().with_elements(vec![Element::text(0.0, 0.0, "hello")]);
+        let options = PdfOptions {
+            fonts: HashMap::from([("Virgil".to_string(), font.clone())]),
+            ..Default::default()
+        };
+        let pdf = scene.to_pdf(&options).unwrap();
+        let base_font = String::from_utf8_lossy(&pdf)
+            .split("/Subtype /Type0 /BaseFont /")
+            .nth(1)
+            .and_then(|rest| rest.split(' ').next())
+            .map(str::to_string)
+            .unwrap();
+        assert_eq!(base_font.find('+'), Some(6));
+        assert!(base_font.ends_with("+ExcalidrawTest-Regular"));
+        // 只嵌入用到的字形
+        let length: usize = String::from_utf8_lossy(&pdf)
+            .split("/Length1 ")
+            .nth(1)
+            .and_then(|rest| rest.split(' ').next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(length < font.len(), "{} >= {}", length, font.len());
+    }
+
+    #[test]
+    fn test_encode_builtin_text() {
+        assert_eq!(encode_builtin_text("a(b)\\é中"), r"(a\(b\)\\\351?)");
+    }
+}
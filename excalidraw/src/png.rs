@@ -0,0 +1,150 @@
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+
+use crate::{
+    encode,
+    error::{Error, Result},
+};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/**
+ * excalidraw 导出 PNG 时写入场景数据所用的 tEXt 关键字
+ */
+const SCENE_KEYWORD: &[u8] = b"application/vnd.excalidraw+json";
+
+struct Chunk<'a> {
+    chunk_type: &'a [u8],
+    data: &'a [u8],
+    /**
+     * 包含长度、类型、数据与 CRC 的完整字节
+     */
+    raw: &'a [u8],
+}
+
+fn read_chunks(png: &[u8]) -> Result<Vec<Chunk<'_>>> {
+    let mut rest = png
+        .strip_prefix(PNG_SIGNATURE)
+        .ok_or_else(|| Error::decode("Not a PNG file"))?;
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let length = rest
+            .get(..4)
+            .map(|length| u32::from_be_bytes([length[0], length[1], length[2], length[3]]))
+            .ok_or_else(|| Error::decode("Truncated PNG chunk"))? as usize;
+        // 32 位平台上 length 加上长度、类型与 CRC 可能溢出
+        let chunk_length = length
+            .checked_add(12)
+            .ok_or_else(|| Error::decode("Invalid PNG chunk length"))?;
+        let raw = rest
+            .get(..chunk_length)
+            .ok_or_else(|| Error::decode("Truncated PNG chunk"))?;
+        chunks.push(Chunk {
+            chunk_type: &raw[4..8],
+            data: &raw[8..8 + length],
+            raw,
+        });
+        rest = &rest[chunk_length..];
+    }
+    Ok(chunks)
+}
+
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(chunk_type);
+    hasher.update(data);
+    png.extend_from_slice(&hasher.finalize().to_be_bytes());
+}
+
+/**
+ * 编码非预乘 alpha 的 RGBA 像素，scene 不为空时以 excalidraw 的格式压缩后写入 tEXt 块
+ */
+pub(crate) fn encode_png(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    scene: Option<&str>,
+) -> Result<Vec<u8>> {
+    // 长度不一致时 PngEncoder 会 panic
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|size| size.checked_mul(4));
+    if expected != Some(pixels.len()) {
+        return Err(Error::encode(format!(
+            "Expected {}x{} RGBA pixels, got {} bytes",
+            width,
+            height,
+            pixels.len()
+        )));
+    }
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(pixels, width, height, ColorType::Rgba8)
+        .map_err(Error::encode)?;
+    let scene = match scene {
+        Some(scene) => scene,
+        None => return Ok(png),
+    };
+
+    // tEXt 为 latin1 文本，EncodedData 的字符都不超过 0xff
+    let mut data = SCENE_KEYWORD.to_vec();
+    data.push(0);
+    data.extend(encode::encode(scene, true).chars().map(|c| c as u8));
+
+    let chunks = read_chunks(&png)?;
+    let mut output = PNG_SIGNATURE.to_vec();
+    for chunk in chunks {
+        if chunk.chunk_type == b"IEND" {
+            write_chunk(&mut output, b"tEXt", &data);
+        }
+        output.extend_from_slice(chunk.raw);
+    }
+    Ok(output)
+}
+
+/**
+ * 从 tEXt 块中取出场景 JSON
+ */
+pub(crate) fn decode_png(png: &[u8]) -> Result<String> {
+    let text = read_chunks(png)?
+        .into_iter()
+        .filter(|chunk| chunk.chunk_type == b"tEXt")
+        .find_map(|chunk| {
+            let (keyword, text) = chunk
+                .data
+                .split_at(chunk.data.iter().position(|b| *b == 0)?);
+            (keyword == SCENE_KEYWORD).then(|| encode::to_byte_string(&text[1..]))
+        })
+        .ok_or_else(|| Error::decode("PNG does not contain scene data"))?;
+    encode::decode_scene_payload(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let pixels = vec![255; 2 * 2 * 4];
+        let scene = r#"{"type":"excalidraw","elements":[]}"#;
+        let png = encode_png(&pixels, 2, 2, Some(scene)).unwrap();
+        assert_eq!(decode_png(&png).unwrap(), scene);
+        assert!(image::load_from_memory(&png).is_ok());
+
+        let png = encode_png(&pixels, 2, 2, None).unwrap();
+        assert!(matches!(decode_png(&png), Err(Error::Decode(_))));
+
+        assert!(matches!(
+            encode_png(&pixels[1..], 2, 2, None),
+            Err(Error::Encode(_))
+        ));
+        assert!(matches!(
+            encode_png(&pixels, u32::MAX, u32::MAX, None),
+            Err(Error::Encode(_))
+        ));
+
+        let png = [PNG_SIGNATURE, &[0xff, 0xff, 0xff, 0xff], b"tEXt"].concat();
+        assert!(matches!(decode_png(&png), Err(Error::Decode(_))));
+    }
+}
@@ -0,0 +1,42 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+const ID_ALPHABET: &[u8] = b"useandom-26T198340PX75pxJACKVERYMINDBUSHWOLF_GQZbfghjklqvwyzrict";
+const ID_LENGTH: usize = 21;
+
+/**
+ * 不依赖随机数库的伪随机数：每次调用使用新的 RandomState 和递增计数器
+ */
+fn next_u64() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+/**
+ * 与 excalidraw 的 randomInteger 一致，用作 seed / versionNonce
+ */
+pub(crate) fn random_integer() -> u64 {
+    next_u64() % 2_u64.pow(31)
+}
+
+/**
+ * 与 excalidraw 的 randomId 一致，生成 21 位的 nanoid 风格 id
+ */
+pub(crate) fn random_id() -> String {
+    let mut id = String::with_capacity(ID_LENGTH);
+    let mut bits = next_u64();
+    for i in 0..ID_LENGTH {
+        // 每个 u64 可以提供 10 个 6 位的字符
+        if i % 10 == 0 && i > 0 {
+            bits = next_u64();
+        }
+        id.push(ID_ALPHABET[(bits & 63) as usize] as char);
+        bits >>= 6;
+    }
+    id
+}
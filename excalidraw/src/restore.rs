@@ -0,0 +1,410 @@
+use std::fmt;
+
+use serde_json::{json, Map, Value};
+
+const KNOWN_ELEMENT_TYPES: [&str; 11] = [
+    "rectangle",
+    "diamond",
+    "ellipse",
+    "arrow",
+    "line",
+    "text",
+    "freedraw",
+    "image",
+    "frame",
+    "magicframe",
+    "selection",
+];
+
+/**
+ * restore 过程中对场景做出的一处修复
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    /**
+     * 被修复字段的路径，如 `elements[0].seed`、`appState.viewBackgroundColor`
+     */
+    pub path: String,
+    pub kind: RepairKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepairKind {
+    /**
+     * 字段缺失或为 null，使用默认值补齐
+     */
+    Defaulted(Value),
+    /**
+     * 旧版本字段迁移为新字段
+     */
+    Migrated { from: String, to: Value },
+    /**
+     * 无法识别的元素被丢弃
+     */
+    Removed(String),
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RepairKind::Defaulted(value) => write!(f, "{}: defaulted to {}", self.path, value),
+            RepairKind::Migrated { from, to } => {
+                write!(f, "{}: migrated from `{}` to {}", self.path, from, to)
+            }
+            RepairKind::Removed(reason) => write!(f, "{}: removed ({})", self.path, reason),
+        }
+    }
+}
+
+/**
+ * 与 excalidraw 的 restoreElements/restoreAppState 对应：补齐缺失字段、迁移旧字段，返回所有修复记录
+ */
+pub fn restore(scene: &mut Value) -> Vec<Repair> {
+    let mut restorer = Restorer::default();
+    if !scene.is_object() {
+        *scene = json!({});
+    }
+    let scene = scene.as_object_mut().unwrap();
+    restorer.ensure(scene, "", "type", json!("excalidraw"));
+    restorer.ensure(scene, "", "version", json!(2));
+    restorer.ensure(scene, "", "source", json!("https://excalidraw.com"));
+    restorer.ensure(scene, "", "elements", json!([]));
+    restorer.ensure(scene, "", "appState", json!({}));
+    restorer.ensure(scene, "", "files", json!({}));
+
+    if let Some(Value::Array(elements)) = scene.get_mut("elements") {
+        let mut restored = Vec::with_capacity(elements.len());
+        for (index, mut element) in elements.drain(..).enumerate() {
+            if restorer.restore_element(&mut element, index) {
+                restored.push(element);
+            }
+        }
+        *elements = restored;
+    }
+    if let Some(Value::Object(app_state)) = scene.get_mut("appState") {
+        restorer.ensure(
+            app_state,
+            "appState",
+            "viewBackgroundColor",
+            json!("#ffffff"),
+        );
+    }
+    restorer.repairs
+}
+
+#[derive(Default)]
+struct Restorer {
+    repairs: Vec<Repair>,
+}
+
+impl Restorer {
+    fn push(&mut self, parent: &str, key: &str, kind: RepairKind) {
+        let path = if parent.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", parent, key)
+        };
+        self.repairs.push(Repair { path, kind });
+    }
+
+    /**
+     * 字段缺失或为 null 时写入默认值
+     */
+    fn ensure(&mut self, object: &mut Map<String, Value>, parent: &str, key: &str, value: Value) {
+        if is_missing(object, key) {
+            self.push(parent, key, RepairKind::Defaulted(value.clone()));
+            object.insert(key.to_string(), value);
+        }
+    }
+
+    /**
+     * 返回 false 表示元素无法恢复，应当丢弃
+     */
+    fn restore_element(&mut self, element: &mut Value, index: usize) -> bool {
+        let path = format!("elements[{}]", index);
+        let element = match element.as_object_mut() {
+            Some(element) => element,
+            None => {
+                self.repairs.push(Repair {
+                    path,
+                    kind: RepairKind::Removed("not an object".to_string()),
+                });
+                return false;
+            }
+        };
+        let path = path.as_str();
+
+        // 旧版本的自由绘制类型为 draw，对应现在的 line
+        if element.get("type").and_then(Value::as_str) == Some("draw") {
+            self.migrate(element, path, "type", "type", json!("line"));
+        }
+        let element_type = match element.get("type").and_then(Value::as_str) {
+            Some(element_type) if KNOWN_ELEMENT_TYPES.contains(&element_type) => {
+                element_type.to_string()
+            }
+            element_type => {
+                let reason = format!("unknown element type {:?}", element_type);
+                self.push(path, "type", RepairKind::Removed(reason));
+                return false;
+            }
+        };
+
+        self.ensure(element, path, "id", json!(format!("restored-{}", index)));
+        self.ensure(element, path, "x", json!(0));
+        self.ensure(element, path, "y", json!(0));
+        self.ensure(element, path, "width", json!(0));
+        self.ensure(element, path, "height", json!(0));
+        self.ensure(element, path, "angle", json!(0));
+        self.ensure(element, path, "strokeColor", json!("#1e1e1e"));
+        self.ensure(element, path, "backgroundColor", json!("transparent"));
+        self.ensure(element, path, "fillStyle", json!("solid"));
+        self.ensure(element, path, "strokeWidth", json!(2));
+        self.ensure(element, path, "strokeStyle", json!("solid"));
+        self.ensure(element, path, "roughness", json!(1));
+        self.ensure(element, path, "opacity", json!(100));
+        self.ensure(element, path, "seed", json!(1));
+        self.ensure(element, path, "version", json!(1));
+        self.ensure(element, path, "versionNonce", json!(0));
+        self.ensure(element, path, "isDeleted", json!(false));
+        // 保证 restore 结果可重复，不使用当前时间
+        self.ensure(element, path, "updated", json!(1));
+        self.ensure(element, path, "locked", json!(false));
+        self.restore_roundness(element, path, &element_type);
+        self.restore_bound_elements(element, path);
+
+        match element_type.as_str() {
+            "text" => self.restore_text(element, path),
+            "arrow" | "line" => self.restore_linear(element, path, &element_type),
+            "freedraw" => {
+                self.ensure(element, path, "points", json!([]));
+                self.ensure(element, path, "pressures", json!([]));
+                self.ensure(element, path, "simulatePressure", json!(true));
+            }
+            "image" => {
+                self.ensure(element, path, "status", json!("pending"));
+                self.ensure(element, path, "scale", json!([1, 1]));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn migrate(
+        &mut self,
+        element: &mut Map<String, Value>,
+        path: &str,
+        from: &str,
+        to: &str,
+        value: Value,
+    ) {
+        if from != to {
+            element.remove(from);
+        }
+        self.push(
+            path,
+            to,
+            RepairKind::Migrated {
+                from: from.to_string(),
+                to: value.clone(),
+            },
+        );
+        element.insert(to.to_string(), value);
+    }
+
+    /**
+     * strokeSharpness: "round" 迁移为 roundness，矩形使用自适应圆角
+     */
+    fn restore_roundness(
+        &mut self,
+        element: &mut Map<String, Value>,
+        path: &str,
+        element_type: &str,
+    ) {
+        let stroke_sharpness = match element.get("strokeSharpness") {
+            Some(value) => value.as_str().map(str::to_string),
+            None => return,
+        };
+        if is_missing(element, "roundness") {
+            let roundness = match stroke_sharpness.as_deref() {
+                Some("round") if matches!(element_type, "rectangle" | "image") => {
+                    json!({ "type": 3 })
+                }
+                Some("round") => json!({ "type": 2 }),
+                _ => Value::Null,
+            };
+            self.migrate(element, path, "strokeSharpness", "roundness", roundness);
+        } else {
+            element.remove("strokeSharpness");
+        }
+    }
+
+    /**
+     * boundElementIds 为旧版本字段，绑定的都是箭头
+     */
+    fn restore_bound_elements(&mut self, element: &mut Map<String, Value>, path: &str) {
+        let ids = match element.get("boundElementIds") {
+            Some(Value::Array(ids)) => ids.clone(),
+            Some(_) => {
+                element.remove("boundElementIds");
+                return;
+            }
+            None => return,
+        };
+        let bound_elements = Value::Array(
+            ids.into_iter()
+                .filter(Value::is_string)
+                .map(|id| json!({ "id": id, "type": "arrow" }))
+                .collect(),
+        );
+        self.migrate(
+            element,
+            path,
+            "boundElementIds",
+            "boundElements",
+            bound_elements,
+        );
+    }
+
+    fn restore_text(&mut self, element: &mut Map<String, Value>, path: &str) {
+        // 旧版本使用 font: "20px Virgil" 描述字体
+        if let Some(font) = element
+            .get("font")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+        {
+            let (size, family) = font.split_once(' ').unwrap_or((font.as_str(), ""));
+            if let Ok(font_size) = size.trim_end_matches("px").parse::<f64>() {
+                if is_missing(element, "fontSize") {
+                    self.migrate(element, path, "font", "fontSize", json!(font_size));
+                }
+            }
+            if let Some(font_family) = get_font_family_id(family) {
+                if is_missing(element, "fontFamily") {
+                    self.migrate(element, path, "font", "fontFamily", json!(font_family));
+                }
+            }
+            element.remove("font");
+        }
+        // fontFamily 可能为字体名或数字字符串
+        match element.get("fontFamily") {
+            Some(Value::String(family)) => {
+                let font_family = get_font_family_id(family).unwrap_or(1);
+                self.migrate(
+                    element,
+                    path,
+                    "fontFamily",
+                    "fontFamily",
+                    json!(font_family),
+                );
+            }
+            // 未知的数字 id 由 FontFamily::Unknown 保留，只修复无法表示为 u8 的值
+            Some(Value::Number(family))
+                if !family.as_u64().is_some_and(|id| u8::try_from(id).is_ok()) =>
+            {
+                self.push(path, "fontFamily", RepairKind::Defaulted(json!(1)));
+                element.insert("fontFamily".to_string(), json!(1));
+            }
+            _ => {}
+        }
+
+        self.ensure(element, path, "text", json!(""));
+        let text = element.get("text").cloned().unwrap_or(json!(""));
+        self.ensure(element, path, "originalText", text);
+        self.ensure(element, path, "fontSize", json!(20));
+        self.ensure(element, path, "fontFamily", json!(1));
+        self.ensure(element, path, "textAlign", json!("left"));
+        self.ensure(element, path, "verticalAlign", json!("top"));
+        let line_height = match element.get("fontFamily").and_then(Value::as_u64) {
+            Some(2) => 1.15,
+            Some(3) => 1.2,
+            _ => 1.25,
+        };
+        self.ensure(element, path, "lineHeight", json!(line_height));
+    }
+
+    fn restore_linear(&mut self, element: &mut Map<String, Value>, path: &str, element_type: &str) {
+        let has_points = element
+            .get("points")
+            .and_then(Value::as_array)
+            .is_some_and(|points| points.len() >= 2);
+        if !has_points {
+            let width = element.get("width").cloned().unwrap_or(json!(0));
+            let height = element.get("height").cloned().unwrap_or(json!(0));
+            let points = json!([[0, 0], [width, height]]);
+            self.push(path, "points", RepairKind::Defaulted(points.clone()));
+            element.insert("points".to_string(), points);
+        }
+        // 箭头默认带终点箭头，显式的 null 表示没有箭头
+        if element_type == "arrow" && !element.contains_key("endArrowhead") {
+            self.push(path, "endArrowhead", RepairKind::Defaulted(json!("arrow")));
+            element.insert("endArrowhead".to_string(), json!("arrow"));
+        }
+    }
+}
+
+fn is_missing(object: &Map<String, Value>, key: &str) -> bool {
+    object.get(key).unwrap_or(&Value::Null).is_null()
+}
+
+fn get_font_family_id(family: &str) -> Option<u8> {
+    match family.trim() {
+        "Virgil" => Some(1),
+        "Helvetica" => Some(2),
+        "Cascadia" => Some(3),
+        "Local Font" => Some(4),
+        "Excalifont" => Some(5),
+        "Nunito" => Some(6),
+        "Lilita One" => Some(7),
+        "Comic Shanns" => Some(8),
+        "Liberation Sans" => Some(9),
+        "Assistant" => Some(10),
+        family => family.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_fills_missing_fields() {
+        let mut scene = json!({
+            "elements": [
+                { "type": "rectangle", "id": "rect", "strokeSharpness": "round" },
+                { "type": "unknown" }
+            ]
+        });
+        let repairs = restore(&mut scene);
+        let element = &scene["elements"][0];
+        assert_eq!(scene["elements"].as_array().unwrap().len(), 1);
+        assert_eq!(element["seed"], json!(1));
+        assert_eq!(element["versionNonce"], json!(0));
+        assert_eq!(element["roundness"], json!({ "type": 3 }));
+        assert!(element.get("strokeSharpness").is_none());
+        assert_eq!(scene["appState"]["viewBackgroundColor"], json!("#ffffff"));
+        assert!(repairs.contains(&Repair {
+            path: "elements[0].roundness".to_string(),
+            kind: RepairKind::Migrated {
+                from: "strokeSharpness".to_string(),
+                to: json!({ "type": 3 }),
+            },
+        }));
+        assert!(repairs
+            .iter()
+            .any(|repair| matches!(repair.kind, RepairKind::Removed(_))));
+    }
+
+    #[test]
+    fn test_restore_legacy_text() {
+        let mut scene = json!({
+            "elements": [{ "type": "text", "id": "text", "text": "hi", "font": "36px Cascadia" }]
+        });
+        restore(&mut scene);
+        let element = &scene["elements"][0];
+        assert_eq!(element["fontSize"], json!(36.0));
+        assert_eq!(element["fontFamily"], json!(3));
+        assert_eq!(element["originalText"], json!("hi"));
+        assert_eq!(element["lineHeight"], json!(1.2));
+        assert!(element.get("font").is_none());
+    }
+}
@@ -0,0 +1,862 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+    ops::RangeBounds,
+    rc::Rc,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+use piet::{
+    kurbo::{Affine, Point, Rect, Shape, Size},
+    Color, Error, FixedGradient, FontFamily, HitTestPoint, HitTestPosition, Image, ImageFormat,
+    InterpolationMode, IntoBrush, LineCap, LineJoin, LineMetric, RenderContext, StrokeStyle, Text,
+    TextAlignment, TextAttribute, TextLayout, TextLayoutBuilder, TextStorage,
+};
+
+use ttf_parser::{Face, GlyphId};
+
+use crate::{draw::Theme, encode, error, files::load_system_font, geometry::estimate_text_size};
+
+/**
+ * 字形基线距离文字顶部的比例
+ */
+const TEXT_ASCENT_RATIO: f64 = 0.8;
+/**
+ * 曲线转换为路径时的精度
+ */
+const PATH_TOLERANCE: f64 = 0.1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    pub padding: f64,
+    /**
+     * 是否绘制 viewBackgroundColor 背景
+     */
+    pub background: bool,
+    /**
+     * 是否在 metadata 中嵌入场景数据，嵌入后可以拖回 excalidraw 继续编辑
+     */
+    pub embed_scene: bool,
+    /**
+     * 深色模式与 excalidraw 的 exportWithDarkMode 一致，图片保持原样
+     */
+    pub theme: Theme,
+    /**
+     * 以字体名称为 key 的字体文件（woff2 / woff / ttf / otf），用于内嵌到 SVG 中，
+     * 找不到时与 PDF 一样从系统字体中查找
+     */
+    pub fonts: HashMap<String, Vec<u8>>,
+    /**
+     * 未提供字体文件时，Virgil 和 Cascadia 引用 excalidraw.com 上的字体而不是内嵌系统字体
+     */
+    pub remote_fonts: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            padding: 10.0,
+            background: true,
+            embed_scene: true,
+            theme: Theme::Light,
+            fonts: HashMap::new(),
+            remote_fonts: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct State {
+    transform: Affine,
+    clip: Option<usize>,
+}
+
+/**
+ * 将 piet 的绘制指令输出为 SVG 元素，绘制逻辑与位图完全共用
+ */
+pub(crate) struct SvgRenderContext {
+    text: SvgText,
+    state: State,
+    stack: Vec<State>,
+    defs: String,
+    body: String,
+    clip_count: usize,
+    fonts: BTreeSet<String>,
+}
+
+impl SvgRenderContext {
+    pub(crate) fn new(options: &SvgOptions) -> Self {
+        Self {
+            text: SvgText(Rc::new(RefCell::new(SvgFonts {
+                custom_fonts: options.fonts.clone(),
+                remote_fonts: options.remote_fonts,
+                fonts: HashMap::new(),
+            }))),
+            state: State::default(),
+            stack: Vec::new(),
+            defs: String::new(),
+            body: String::new(),
+            clip_count: 0,
+            fonts: BTreeSet::new(),
+        }
+    }
+
+    /**
+     * 生成完整的 SVG 文档
+     */
+    pub(crate) fn into_svg(self, width: f64, height: f64, scene: Option<&str>) -> String {
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{w}" height="{h}">"#,
+            w = format_number(width),
+            h = format_number(height),
+        );
+        svg.push_str("<!-- svg-source:excalidraw -->");
+        if let Some(scene) = scene {
+            svg.push_str("<metadata>");
+            svg.push_str(&encode_svg_metadata(scene));
+            svg.push_str("</metadata>");
+        }
+        svg.push_str("<defs>");
+        let font_faces = get_font_faces(&self.fonts, &mut self.text.0.borrow_mut());
+        if !font_faces.is_empty() {
+            svg.push_str(r#"<style class="style-fonts">"#);
+            svg.push_str(&font_faces);
+            svg.push_str("</style>");
+        }
+        svg.push_str(&self.defs);
+        svg.push_str("</defs>");
+        svg.push_str(&self.body);
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /**
+     * 输出一个图形元素，存在裁剪区域时包一层 g，使裁剪路径不受元素自身 transform 影响
+     */
+    fn push_element(&mut self, element: &str) {
+        match self.state.clip {
+            Some(clip) => {
+                let _ = write!(
+                    self.body,
+                    r#"<g clip-path="url(#clip-{})">{}</g>"#,
+                    clip, element
+                );
+            }
+            None => self.body.push_str(element),
+        }
+    }
+
+    fn push_path(&mut self, shape: impl Shape, attributes: &str) {
+        let path = shape.into_path(PATH_TOLERANCE).to_svg();
+        let element = format!(
+            r#"<path d="{}"{}{}/>"#,
+            path,
+            transform_attribute(self.state.transform),
+            attributes
+        );
+        self.push_element(&element);
+    }
+
+    fn push_image(&mut self, image: &SvgImage, src_rect: Rect, dst_rect: Rect) {
+        // 用嵌套 svg 的 viewBox 截取图片的 src_rect 部分，transform 放在外层的 g 上
+        let element = format!(
+            r#"<g{}><svg x="{}" y="{}" width="{}" height="{}" viewBox="{} {} {} {}" preserveAspectRatio="none"><image width="{}" height="{}" href="data:image/png;base64,{}"/></svg></g>"#,
+            transform_attribute(self.state.transform),
+            format_number(dst_rect.x0),
+            format_number(dst_rect.y0),
+            format_number(dst_rect.width()),
+            format_number(dst_rect.height()),
+            format_number(src_rect.x0),
+            format_number(src_rect.y0),
+            format_number(src_rect.width()),
+            format_number(src_rect.height()),
+            image.width,
+            image.height,
+            image.data,
+        );
+        self.push_element(&element);
+    }
+}
+
+impl RenderContext for SvgRenderContext {
+    type Brush = Color;
+    type Image = SvgImage;
+    type Text = SvgText;
+    type TextLayout = SvgTextLayout;
+
+    fn status(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn solid_brush(&mut self, color: Color) -> Self::Brush {
+        color
+    }
+
+    fn gradient(&mut self, _gradient: impl Into<FixedGradient>) -> Result<Self::Brush, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn clear(&mut self, region: impl Into<Option<Rect>>, color: Color) {
+        let element = match region.into() {
+            Some(rect) => format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+                format_number(rect.x0),
+                format_number(rect.y0),
+                format_number(rect.width()),
+                format_number(rect.height()),
+                paint_attributes("fill", &color),
+            ),
+            None => format!(
+                r#"<rect width="100%" height="100%"{}/>"#,
+                paint_attributes("fill", &color)
+            ),
+        };
+        self.body.push_str(&element);
+    }
+
+    fn stroke(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>, width: f64) {
+        self.stroke_styled(shape, brush, width, &StrokeStyle::default());
+    }
+
+    fn stroke_styled(
+        &mut self,
+        shape: impl Shape,
+        brush: &impl IntoBrush<Self>,
+        width: f64,
+        style: &StrokeStyle,
+    ) {
+        let color = brush.make_brush(self, || shape.bounding_box()).into_owned();
+        let attributes = format!(
+            r#" fill="none"{} stroke-width="{}"{}"#,
+            paint_attributes("stroke", &color),
+            format_number(width),
+            stroke_style_attributes(style)
+        );
+        self.push_path(shape, &attributes);
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
+        let color = brush.make_brush(self, || shape.bounding_box()).into_owned();
+        self.push_path(shape, &paint_attributes("fill", &color));
+    }
+
+    fn fill_even_odd(&mut self, shape: impl Shape, brush: &impl IntoBrush<Self>) {
+        let color = brush.make_brush(self, || shape.bounding_box()).into_owned();
+        let attributes = format!(
+            r#"{} fill-rule="evenodd""#,
+            paint_attributes("fill", &color)
+        );
+        self.push_path(shape, &attributes);
+    }
+
+    fn clip(&mut self, shape: impl Shape) {
+        let id = self.clip_count;
+        self.clip_count += 1;
+        // 嵌套裁剪时与上一层裁剪区域取交集
+        let parent = match self.state.clip {
+            Some(parent) => format!(r#" clip-path="url(#clip-{})""#, parent),
+            None => String::new(),
+        };
+        let _ = write!(
+            self.defs,
+            r#"<clipPath id="clip-{}"{}><path d="{}"{}/></clipPath>"#,
+            id,
+            parent,
+            shape.into_path(PATH_TOLERANCE).to_svg(),
+            transform_attribute(self.state.transform)
+        );
+        self.state.clip = Some(id);
+    }
+
+    fn text(&mut self) -> &mut Self::Text {
+        &mut self.text
+    }
+
+    fn draw_text(&mut self, layout: &Self::TextLayout, pos: impl Into<Point>) {
+        let pos = pos.into();
+        self.fonts.insert(layout.font_family.name().to_string());
+        let element = format!(
+            r#"<text x="{}" y="{}"{} font-family="{}" font-size="{}px"{} style="white-space: pre;">{}</text>"#,
+            format_number(pos.x),
+            format_number(pos.y + layout.font_size * TEXT_ASCENT_RATIO),
+            transform_attribute(self.state.transform),
+            escape_xml(&get_font_family_list(&layout.font_family)),
+            format_number(layout.font_size),
+            paint_attributes("fill", &layout.color),
+            escape_xml(&layout.text),
+        );
+        self.push_element(&element);
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.stack.push(self.state);
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        self.state = self.stack.pop().ok_or(Error::StackUnbalance)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        self.state.transform *= transform;
+    }
+
+    fn make_image_with_stride(
+        &mut self,
+        width: usize,
+        height: usize,
+        stride: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<Self::Image, Error> {
+        let bytes_per_pixel = format.bytes_per_pixel();
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for row in 0..height {
+            let start = row * stride;
+            let row = buf
+                .get(start..start + width * bytes_per_pixel)
+                .ok_or(Error::InvalidInput)?;
+            for pixel in row.chunks_exact(bytes_per_pixel) {
+                match format {
+                    ImageFormat::Grayscale => pixels.extend([pixel[0], pixel[0], pixel[0], 255]),
+                    ImageFormat::Rgb => pixels.extend([pixel[0], pixel[1], pixel[2], 255]),
+                    ImageFormat::RgbaSeparate => pixels.extend_from_slice(pixel),
+                    ImageFormat::RgbaPremul => {
+                        // PNG 使用非预乘的 alpha
+                        let alpha = pixel[3] as u16;
+                        let unpremultiply = |channel: u8| match alpha {
+                            0 => 0,
+                            _ => ((channel as u16 * 255 + alpha / 2) / alpha).min(255) as u8,
+                        };
+                        pixels.extend([
+                            unpremultiply(pixel[0]),
+                            unpremultiply(pixel[1]),
+                            unpremultiply(pixel[2]),
+                            pixel[3],
+                        ]);
+                    }
+                    _ => return Err(Error::NotSupported),
+                }
+            }
+        }
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png)
+            .write_image(&pixels, width as u32, height as u32, ColorType::Rgba8)
+            .map_err(|e| Error::BackendError(Box::new(e)))?;
+        Ok(SvgImage {
+            width,
+            height,
+            data: STANDARD.encode(png),
+        })
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &Self::Image,
+        dst_rect: impl Into<Rect>,
+        _interp: InterpolationMode,
+    ) {
+        let src_rect = Rect::new(0.0, 0.0, image.width as f64, image.height as f64);
+        self.push_image(image, src_rect, dst_rect.into());
+    }
+
+    fn draw_image_area(
+        &mut self,
+        image: &Self::Image,
+        src_rect: impl Into<Rect>,
+        dst_rect: impl Into<Rect>,
+        _interp: InterpolationMode,
+    ) {
+        self.push_image(image, src_rect.into(), dst_rect.into());
+    }
+
+    fn capture_image_area(&mut self, _src_rect: impl Into<Rect>) -> Result<Self::Image, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, _blur_radius: f64, brush: &impl IntoBrush<Self>) {
+        self.fill(rect, brush);
+    }
+
+    fn current_transform(&self) -> Affine {
+        self.state.transform
+    }
+}
+
+/**
+ * PNG 编码后以 base64 形式内嵌
+ */
+#[derive(Debug, Clone)]
+pub(crate) struct SvgImage {
+    width: usize,
+    height: usize,
+    data: String,
+}
+
+impl Image for SvgImage {
+    fn size(&self) -> Size {
+        Size::new(self.width as f64, self.height as f64)
+    }
+}
+
+/**
+ * SVG 中用到的字体，依次查找传入的字体文件、同名的系统字体。
+ * 字体文件会完整内嵌，找不到时不用通用字体代替，交给浏览器按 font-family 回退
+ */
+struct SvgFonts {
+    custom_fonts: HashMap<String, Vec<u8>>,
+    remote_fonts: bool,
+    fonts: HashMap<String, Option<Rc<[u8]>>>,
+}
+
+impl SvgFonts {
+    /**
+     * 按名称查找要内嵌的字体文件，找不到时返回 None
+     */
+    fn resolve_font(&mut self, name: &str) -> Option<Rc<[u8]>> {
+        if let Some(data) = self.fonts.get(name) {
+            return data.clone();
+        }
+        let data = match self.custom_fonts.get(name) {
+            Some(data) => Some(data.clone()),
+            None if self.remote_fonts && get_remote_font_url(name).is_some() => None,
+            None => load_system_font(name),
+        };
+        let data: Option<Rc<[u8]>> = data.map(Rc::from);
+        self.fonts.insert(name.to_string(), data.clone());
+        data
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SvgText(Rc<RefCell<SvgFonts>>);
+
+impl Text for SvgText {
+    type TextLayout = SvgTextLayout;
+    type TextLayoutBuilder = SvgTextLayoutBuilder;
+
+    fn font_family(&mut self, family_name: &str) -> Option<FontFamily> {
+        // 字体在生成 SVG 时统一内嵌，这里只记录名称
+        Some(FontFamily::new_unchecked(family_name))
+    }
+
+    fn load_font(&mut self, _data: &[u8]) -> Result<FontFamily, Error> {
+        Err(Error::NotSupported)
+    }
+
+    fn new_text_layout(&mut self, text: impl TextStorage) -> Self::TextLayoutBuilder {
+        SvgTextLayoutBuilder {
+            fonts: self.0.clone(),
+            layout: SvgTextLayout {
+                text: text.as_str().to_string(),
+                font_family: FontFamily::SANS_SERIF,
+                font_size: piet::util::DEFAULT_FONT_SIZE,
+                color: piet::util::DEFAULT_TEXT_COLOR,
+                width: 0.0,
+                trailing_whitespace_width: 0.0,
+            },
+        }
+    }
+}
+
+pub(crate) struct SvgTextLayoutBuilder {
+    fonts: Rc<RefCell<SvgFonts>>,
+    layout: SvgTextLayout,
+}
+
+impl TextLayoutBuilder for SvgTextLayoutBuilder {
+    type Out = SvgTextLayout;
+
+    fn max_width(self, _width: f64) -> Self {
+        self
+    }
+
+    fn alignment(self, _alignment: TextAlignment) -> Self {
+        self
+    }
+
+    fn default_attribute(mut self, attribute: impl Into<TextAttribute>) -> Self {
+        match attribute.into() {
+            TextAttribute::FontFamily(font_family) => self.layout.font_family = font_family,
+            TextAttribute::FontSize(font_size) => self.layout.font_size = font_size,
+            TextAttribute::TextColor(color) => self.layout.color = color,
+            _ => {}
+        }
+        self
+    }
+
+    fn range_attribute(
+        self,
+        _range: impl RangeBounds<usize>,
+        _attribute: impl Into<TextAttribute>,
+    ) -> Self {
+        self
+    }
+
+    fn build(mut self) -> Result<Self::Out, Error> {
+        let layout = &mut self.layout;
+        let data = self
+            .fonts
+            .borrow_mut()
+            .resolve_font(layout.font_family.name());
+        let trailing = &layout.text[layout.text.trim_end().len()..];
+        // woff / woff2 无法直接解析，与找不到字体时一样按字符数估算
+        match data.as_deref().and_then(|data| Face::parse(data, 0).ok()) {
+            Some(face) => {
+                layout.width = get_text_width(&face, &layout.text, layout.font_size);
+                layout.trailing_whitespace_width =
+                    get_text_width(&face, trailing, layout.font_size);
+            }
+            None => {
+                layout.width = estimate_text_size(&layout.text, layout.font_size, 0.0).0;
+                layout.trailing_whitespace_width =
+                    estimate_text_size(trailing, layout.font_size, 0.0).0;
+            }
+        }
+        Ok(self.layout)
+    }
+}
+
+/**
+ * 按字体中字形的前进宽度计算文字宽度
+ */
+fn get_text_width(face: &Face, text: &str, font_size: f64) -> f64 {
+    let advance: u32 = text
+        .chars()
+        .map(|c| {
+            let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+            face.glyph_hor_advance(glyph).unwrap_or(0) as u32
+        })
+        .sum();
+    advance as f64 * font_size / face.units_per_em() as f64
+}
+
+/**
+ * 单行文字，宽度按字体的字形宽度计算，没有可用的字体时按字符数估算
+ */
+#[derive(Debug, Clone)]
+pub(crate) struct SvgTextLayout {
+    text: String,
+    font_family: FontFamily,
+    font_size: f64,
+    color: Color,
+    width: f64,
+    trailing_whitespace_width: f64,
+}
+
+impl TextLayout for SvgTextLayout {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.font_size)
+    }
+
+    fn trailing_whitespace_width(&self) -> f64 {
+        self.trailing_whitespace_width
+    }
+
+    fn image_bounds(&self) -> Rect {
+        self.size().to_rect()
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn line_text(&self, line_number: usize) -> Option<&str> {
+        (line_number == 0).then_some(self.text.as_str())
+    }
+
+    fn line_metric(&self, line_number: usize) -> Option<LineMetric> {
+        (line_number == 0).then(|| LineMetric {
+            start_offset: 0,
+            end_offset: self.text.len(),
+            trailing_whitespace: self.text.len() - self.text.trim_end().len(),
+            baseline: self.font_size * TEXT_ASCENT_RATIO,
+            height: self.font_size,
+            y_offset: 0.0,
+        })
+    }
+
+    fn line_count(&self) -> usize {
+        1
+    }
+
+    fn hit_test_point(&self, _point: Point) -> HitTestPoint {
+        HitTestPoint::default()
+    }
+
+    fn hit_test_text_position(&self, _idx: usize) -> HitTestPosition {
+        HitTestPosition::default()
+    }
+}
+
+/**
+ * 与 excalidraw 导出的 SVG 相同的 metadata 格式，excalidraw 可以从中还原场景
+ */
+fn encode_svg_metadata(json: &str) -> String {
+    // payload 中的字符都不超过 0xff，按 latin1 转回字节后再做 base64
+    let bytes: Vec<u8> = encode::encode(json, true)
+        .chars()
+        .map(|c| c as u8)
+        .collect();
+    format!(
+        "<!-- payload-type:application/vnd.excalidraw+json --><!-- payload-version:2 --><!-- payload-start -->{}<!-- payload-end -->",
+        STANDARD.encode(bytes)
+    )
+}
+
+/**
+ * 读取 SVG metadata 中的场景，payload-version 为 1 时 base64 内容为 UTF-8 文本，否则为 bstring
+ */
+pub(crate) fn decode_svg_metadata(svg: &str) -> error::Result<String> {
+    if !svg.contains("payload-type:application/vnd.excalidraw+json") {
+        return Err(error::Error::decode("SVG does not contain scene data"));
+    }
+    let payload = svg
+        .split("<!-- payload-start -->")
+        .nth(1)
+        .and_then(|rest| rest.split("<!-- payload-end -->").next())
+        .ok_or_else(|| error::Error::decode("SVG does not contain scene data"))?;
+    let bytes = STANDARD
+        .decode(payload.trim())
+        .map_err(error::Error::decode)?;
+    let text = match svg.contains("<!-- payload-version:1 -->") || !svg.contains("payload-version:")
+    {
+        true => String::from_utf8(bytes).map_err(error::Error::decode)?,
+        false => encode::to_byte_string(&bytes),
+    };
+    encode::decode_scene_payload(&text)
+}
+
+/**
+ * 为用到的字体生成 @font-face，字体文件内嵌为 data URL。
+ * 开启 remote_fonts 且没有提供字体文件时引用 excalidraw.com 上的字体
+ */
+fn get_font_faces(fonts: &BTreeSet<String>, svg_fonts: &mut SvgFonts) -> String {
+    let mut font_faces = String::new();
+    for name in fonts {
+        let src = match svg_fonts.resolve_font(name) {
+            Some(data) => format!(
+                "data:{};base64,{}",
+                get_font_mime_type(&data),
+                STANDARD.encode(data)
+            ),
+            None => match get_remote_font_url(name).filter(|_| svg_fonts.remote_fonts) {
+                Some(url) => url.to_string(),
+                None => continue,
+            },
+        };
+        let _ = write!(
+            font_faces,
+            r#"@font-face {{ font-family: "{}"; src: url("{}"); }}"#,
+            escape_xml(name),
+            src
+        );
+    }
+    font_faces
+}
+
+fn get_remote_font_url(name: &str) -> Option<&'static str> {
+    match name {
+        "Virgil" => Some("https://excalidraw.com/Virgil.woff2"),
+        "Cascadia" => Some("https://excalidraw.com/Cascadia.woff2"),
+        _ => None,
+    }
+}
+
+fn get_font_mime_type(data: &[u8]) -> &'static str {
+    match data.get(..4) {
+        Some(b"wOF2") => "font/woff2",
+        Some(b"wOFF") => "font/woff",
+        Some(b"OTTO") => "font/otf",
+        _ => "font/ttf",
+    }
+}
+
+/**
+ * 字体缺失时回退到通用字体
+ */
+fn get_font_family_list(font_family: &FontFamily) -> String {
+    let fallback = match font_family.name() {
+        "Cascadia" | "Comic Shanns" => "monospace",
+        _ => "sans-serif",
+    };
+    format!("{}, {}", font_family.name(), fallback)
+}
+
+fn paint_attributes(name: &str, color: &Color) -> String {
+    let (r, g, b, a) = color.as_rgba8();
+    let mut attributes = format!(r##" {}="#{:02x}{:02x}{:02x}""##, name, r, g, b);
+    if a < 255 {
+        let _ = write!(
+            attributes,
+            r#" {}-opacity="{}""#,
+            name,
+            format_number(a as f64 / 255.0)
+        );
+    }
+    attributes
+}
+
+fn stroke_style_attributes(style: &StrokeStyle) -> String {
+    let mut attributes = String::new();
+    match style.line_join {
+        LineJoin::Miter { limit } => {
+            let _ = write!(
+                attributes,
+                r#" stroke-miterlimit="{}""#,
+                format_number(limit)
+            );
+        }
+        LineJoin::Round => attributes.push_str(r#" stroke-linejoin="round""#),
+        LineJoin::Bevel => attributes.push_str(r#" stroke-linejoin="bevel""#),
+    }
+    match style.line_cap {
+        LineCap::Butt => {}
+        LineCap::Round => attributes.push_str(r#" stroke-linecap="round""#),
+        LineCap::Square => attributes.push_str(r#" stroke-linecap="square""#),
+    }
+    if !style.dash_pattern.is_empty() {
+        let dashes: Vec<String> = style
+            .dash_pattern
+            .iter()
+            .map(|d| format_number(*d))
+            .collect();
+        let _ = write!(attributes, r#" stroke-dasharray="{}""#, dashes.join(" "));
+        if style.dash_offset != 0.0 {
+            let _ = write!(
+                attributes,
+                r#" stroke-dashoffset="{}""#,
+                format_number(style.dash_offset)
+            );
+        }
+    }
+    attributes
+}
+
+fn transform_attribute(transform: Affine) -> String {
+    if transform == Affine::IDENTITY {
+        return String::new();
+    }
+    let coeffs: Vec<String> = transform
+        .as_coeffs()
+        .iter()
+        .map(|c| format_number(*c))
+        .collect();
+    format!(r#" transform="matrix({})""#, coeffs.join(" "))
+}
+
+/**
+ * 保留 3 位小数并去掉多余的 0，减小输出体积
+ */
+pub(crate) fn format_number(value: f64) -> String {
+    let value = format!("{:.3}", value);
+    let value = value.trim_end_matches('0').trim_end_matches('.');
+    match value {
+        "-0" => "0".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn escape_xml(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::Element, Excalidraw};
+
+    #[test]
+    fn test_to_svg() {
+        let scene = Excalidraw::new().with_elements(vec![
+            Element::rectangle(0.0, 0.0, 100.0, 50.0).with_seed(1),
+            Element::text(0.0, 80.0, "<a & b>").with_seed(2),
+        ]);
+        let svg = scene.to_svg(&SvgOptions::default()).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains("<path d=\"M"));
+        assert!(svg.contains("&lt;a &amp; b&gt;"));
+        assert!(svg.contains(r#"font-family="Virgil, sans-serif""#));
+        assert!(svg.contains("<!-- payload-start -->"));
+    }
+
+    #[test]
+    fn test_svg_fonts() {
+        let scene = Excalidraw::new().with_elements(vec![Element::text(0.0, 0.0, "hello")]);
+        let svg = scene.to_svg(&SvgOptions::default()).unwrap();
+        assert!(!svg.contains("https://excalidraw.com/"));
+
+        let options = SvgOptions {
+            remote_fonts: true,
+            ..Default::default()
+        };
+        let svg = scene.to_svg(&options).unwrap();
+        assert!(svg.contains("https://excalidraw.com/Virgil.woff2"));
+
+        // 提供的字体文件会被内嵌，文字宽度按字形宽度计算
+        let font = include_bytes!("../tests/fixtures/excalidraw-test.ttf").to_vec();
+        let face = Face::parse(&font, 0).unwrap();
+        let width = get_text_width(&face, "hello", 20.0);
+        assert_eq!(width, 61.0);
+        let mut text = SvgRenderContext::new(&SvgOptions {
+            fonts: HashMap::from([("Virgil".to_string(), font.clone())]),
+            ..Default::default()
+        })
+        .text;
+        let layout = text
+            .new_text_layout("hello")
+            .font(FontFamily::new_unchecked("Virgil"), 20.0)
+            .build()
+            .unwrap();
+        assert_eq!(layout.size().width, width);
+        let options = SvgOptions {
+            fonts: HashMap::from([("Virgil".to_string(), font)]),
+            ..options
+        };
+        let svg = scene.to_svg(&options).unwrap();
+        assert!(svg.contains(r#"src: url("data:font/ttf;base64,"#));
+        assert!(!svg.contains("https://excalidraw.com/"));
+    }
+
+    #[test]
+    fn test_svg_metadata_payload() {
+        let metadata = encode_svg_metadata(r#"{"text":"中文"}"#);
+        let payload = metadata
+            .split("<!-- payload-start -->")
+            .nth(1)
+            .and_then(|rest| rest.split("<!-- payload-end -->").next())
+            .unwrap();
+        let bytes = STANDARD.decode(payload).unwrap();
+        let json: String = bytes.into_iter().map(char::from).collect();
+        assert_eq!(encode::decode(&json).unwrap(), r#"{"text":"中文"}"#);
+
+        let scene = Excalidraw::new().with_elements(vec![Element::ellipse(0.0, 0.0, 10.0, 10.0)]);
+        let svg = scene.to_svg(&SvgOptions::default()).unwrap();
+        assert_eq!(Excalidraw::from_svg(&svg).unwrap(), scene);
+    }
+}